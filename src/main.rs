@@ -1,3 +1,6 @@
+//House style: explicit returns, variables assigned from a match, spelled-out 'static lifetimes, state::state, and match even for a single pattern
+#![allow(clippy::needless_return, clippy::needless_late_init, clippy::redundant_static_lifetimes, clippy::module_inception, clippy::single_match)]
+
 mod util;
 mod state;
 
@@ -9,31 +12,84 @@ use ggez::{
         WindowSetup
     }
 };
-use util::data_generator;
+use util::{data_generator, input_recording::InputReplay, viewport};
 use state::{
-    game_object,
+    game_mode::GameMode,
+    menu::MainMenuScene,
+    tile,
     state::GameState
 };
 
+const USAGE: &'static str = "Usage: any4 [endless|blitz|timelimit|survival|practice] [--seed <n>] [--record <file> | --replay <file>]";
+
 fn main() {
-    let mut state  = GameState::new();
     data_generator::init(1, 10, 4, false);
-    let config = data_generator::SetConfig::new(10, None, Some(data_generator::value_is_positive_integer), vec!(data_generator::InputDifficulty::Easy, data_generator::InputDifficulty::Moderate, data_generator::InputDifficulty::Hard));
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mode_name = args.iter().enumerate() //The first argument that isn't a flag or a flag's value
+        .find(|(i, a)| !a.starts_with("--") && (*i == 0 || !args[i-1].starts_with("--")))
+        .map(|(_, a)| a);
+    let mode = match mode_name {
+        Some(name) => match GameMode::from_name(name) {
+            Some(mode) => mode,
+            None => { exit_with_usage(&format!("Unknown game mode {:?}", name)); }
+        },
+        None => GameMode::Endless
+    };
     
+    //--replay <file> plays a recording back; --record <file> writes one on quit, dealing boards from --seed <n> (random by default)
+    let mut seed = match get_flag_value(&args, "--seed") {
+        Some(value) => match value.parse::<u64>() {
+            Ok(seed) => Some(seed),
+            Err(_) => { exit_with_usage(&format!("--seed expects a whole number, not {:?}", value)); }
+        },
+        None => None
+    };
+    let mut state  = GameState::new();
+    if let Some(path) = get_flag_value(&args, "--replay") {
+        let replay = match InputReplay::load(path) {
+            Ok(replay) => replay,
+            Err(e) => {
+                eprintln!("Could not load input recording {:?}: {}", path, e);
+                std::process::exit(1);
+            }
+        };
+        seed = Some(replay.get_seed());
+        state.start_replay(replay);
+    }
+    else if let Some(path) = get_flag_value(&args, "--record") {
+        let record_seed = seed.unwrap_or_else(rand::random);
+        seed = Some(record_seed);
+        state.start_recording(record_seed, path);
+    }
+
     let pool_map = data_generator::get_deserialized_input_data_pool_map().expect("");
-    state.add_controllable(Box::new(game_object::GameController::new(game_object::BoardContainer::new(pool_map, config))));
+    state.push_scene(Box::new(MainMenuScene::new(pool_map, mode).with_seed(seed)));
 
     /* Main game loop */
     let (ctx, event_loop) = ContextBuilder::new("any4", "Act-Novel")
         .window_setup(WindowSetup::default().title("Any4"))
         .window_mode(
             WindowMode::default()
-                .dimensions(960.0, 540.0)
+                .dimensions(viewport::REFERENCE_WIDTH, viewport::REFERENCE_HEIGHT)
                 .resizable(true)
         )
         .build()
         .unwrap();
+    tile::init_tile_meshes(&ctx).expect("Could not build tile meshes");
 
     event::run(ctx, event_loop, state);
 }
 
+
+//None if the flag wasn't passed. A flag without a value is a usage error
+fn get_flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    let i = args.iter().position(|a| a == flag)?;
+    match args.get(i+1).filter(|v| !v.starts_with("--")) {
+        Some(value) => Some(value.as_str()),
+        None => { exit_with_usage(&format!("{} expects a value", flag)); }
+    }
+}
+fn exit_with_usage(message: &str) -> ! {
+    eprintln!("{}\n{}", message, USAGE);
+    std::process::exit(2);
+}
@@ -1,2 +1,7 @@
 pub mod game_object;
+pub mod tile;
+pub mod game_mode;
+pub mod object_tree;
+pub mod scene;
+pub mod menu;
 pub mod state;
\ No newline at end of file
@@ -0,0 +1,89 @@
+use crate::util::{data_generator::InputDifficulty, animation::AnimationInputPolicy};
+
+const BLITZ_DURATION: f32 = 180.0;
+const BOARD_TIME_LIMIT: f32 = 30.0;
+const SURVIVAL_LIVES: u32 = 3;
+const SURVIVAL_BOARD_TIME_LIMIT: f32 = 45.0;
+const SURVIVAL_STREAK_PER_TIER: u32 = 3; //Consecutive solves needed to move up a difficulty tier
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameMode {
+    Endless,
+    Blitz { round_duration: f32 }, //Solve as many boards as possible before the clock runs out
+    TimeLimit { board_duration: f32 }, //Every board has its own clock; running out forfeits the board
+    Survival { lives: u32, board_duration: f32 }, //Skips and timeouts cost a life; difficulty climbs with the streak
+    Practice //Untimed; every solve stops on a breakdown of the board's solutions
+}
+impl GameMode {
+    pub fn blitz() -> GameMode {
+        GameMode::Blitz { round_duration: BLITZ_DURATION }
+    }
+    pub fn time_limit() -> GameMode {
+        GameMode::TimeLimit { board_duration: BOARD_TIME_LIMIT }
+    }
+    pub fn survival() -> GameMode {
+        GameMode::Survival { lives: SURVIVAL_LIVES, board_duration: SURVIVAL_BOARD_TIME_LIMIT }
+    }
+    pub fn from_name(name: &str) -> Option<GameMode> {
+        match name.to_lowercase().as_str() {
+            "endless" => Some(GameMode::Endless),
+            "blitz" => Some(GameMode::blitz()),
+            "timelimit" | "time-limit" => Some(GameMode::time_limit()),
+            "survival" => Some(GameMode::survival()),
+            "practice" | "zen" => Some(GameMode::Practice),
+            _ => None
+        }
+    }
+    pub fn get_name(&self) -> String {
+        match self {
+            GameMode::Endless => "Endless".to_string(),
+            GameMode::Blitz { .. } => "Blitz".to_string(),
+            GameMode::TimeLimit { .. } => "Time Limit".to_string(),
+            GameMode::Survival { .. } => "Survival".to_string(),
+            GameMode::Practice => "Practice".to_string()
+        }
+    }
+    pub fn get_round_duration(&self) -> Option<f32> {
+        match self {
+            GameMode::Blitz { round_duration } => Some(*round_duration),
+            _ => None
+        }
+    }
+    pub fn get_board_duration(&self) -> Option<f32> {
+        match self {
+            GameMode::TimeLimit { board_duration } => Some(*board_duration),
+            GameMode::Survival { board_duration, .. } => Some(*board_duration),
+            _ => None
+        }
+    }
+    /// Whether the round is over once the configured board sequence has been played through.
+    pub fn ends_with_sequence(&self) -> bool {
+        matches!(self, GameMode::TimeLimit { .. })
+    }
+    pub fn get_lives(&self) -> Option<u32> {
+        match self {
+            GameMode::Survival { lives, .. } => Some(*lives),
+            _ => None
+        }
+    }
+}
+
+/// Options that can be changed in the middle of a game from the pause menu.
+#[derive(Clone, Debug)]
+pub struct GameSettings {
+    pub difficulties: Vec<InputDifficulty>,
+    pub hints_enabled: bool,
+    pub animation_input: AnimationInputPolicy
+}
+
+/// Survival difficulty tier for a given streak: Easy, then Moderate, then Hard.
+pub fn get_survival_tier(streak: u32) -> usize {
+    ((streak/SURVIVAL_STREAK_PER_TIER) as usize).min(2)
+}
+pub fn get_survival_tier_difficulties(tier: usize) -> Vec<InputDifficulty> {
+    match tier {
+        0 => vec!(InputDifficulty::Easy),
+        1 => vec!(InputDifficulty::Moderate),
+        _ => vec!(InputDifficulty::Hard)
+    }
+}
@@ -1,72 +1,185 @@
 use crate::util::{
     input_manager::{InputSemantic, InputState, InputManager},
-    data_generator::{self, OpType, DifficultyPools, SetConfig, Board}
+    data_generator::{self, OpType, DifficultyPools, SetConfig, Board, InputDifficulty},
+    expression_parser,
+    solver,
+    scoring::{ScoreKeeper, SolveRecord},
+    timer::{self, Countdown},
+    viewport::Viewport,
+    layout::{ListLayout, GridLayout},
+    animation::{Animation, AnimationInputPolicy, Easing, Pose},
+    text_input::{TextField, TextInputChannel},
+    registry::Handle
+};
+use crate::state::{
+    game_mode::{self, GameMode, GameSettings},
+    object_tree::ObjectTree,
+    tile::{Tile, TileState}
 };
 use fraction::Fraction;
-use ggez::{graphics::{self, Text, Drawable, Canvas, Color}, mint::Point2};
-use std::collections::HashMap;
+use rand::{SeedableRng, rngs::StdRng};
+use ggez::{graphics::{self, Text, TextLayout, Drawable, Canvas, Color}, mint::Point2};
+use std::collections::{HashMap, VecDeque};
+use log::{error, info, warn};
 use queues::*;
 
-pub trait GameObject: AsGameObject {
-    fn draw(&mut self, _canvas: &mut Canvas) {}
+pub trait GameObject {
+    /// `_parent` is the world transform of whatever this object hangs off of; the object's own
+    /// transform is relative to it.
+    fn draw(&mut self, _canvas: &mut Canvas, _parent: &Transform) {}
     fn get_depth(&self) -> i32 { 0 }
-    fn update(&mut self) {}
+    fn get_transform(&self) -> Transform { Transform::new(0.0, 0.0, self.get_depth()) }
+    fn update(&mut self, _delta: f32) {}
 }
 pub trait ControllableGameObject : GameObject {
     fn process_input(&mut self, _input_manager: &InputManager) {}
+    /// Whether this object should be given focus for typed text. Only one consumer gets it per frame.
+    fn wants_text_input(&self) -> bool { false }
+    fn process_text_input(&mut self, _channel: &mut TextInputChannel) {}
 }
 
-pub trait AsGameObject {
-    fn as_game_object(&self) -> &dyn GameObject;
-    fn as_mut_game_object(&mut self) -> &mut dyn GameObject;
-}
-impl <T: GameObject> AsGameObject for T {
-    fn as_game_object(&self) -> &dyn GameObject {
-        self
-    }
-    fn as_mut_game_object(&mut self) -> &mut dyn GameObject {
-        self
-    }
-}
-
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Transform {
     x: f32,
     y: f32,
-    depth: i32
+    depth: i32,
+    scale: f32 //Applies to this object and everything positioned relative to it
+}
+impl Transform {
+    pub fn new(x: f32, y: f32, depth: i32) -> Transform {
+        Transform { x, y, depth, scale: 1.0 }
+    }
+    pub fn origin() -> Transform {
+        Transform::new(0.0, 0.0, 0)
+    }
+    /// Root for layouts authored at the reference resolution, pinned to a fraction of the window
+    /// (e.g. (0.5, 0.5) for the center) and scaled to fit it.
+    pub fn anchored(viewport: &Viewport, x_percent: f32, y_percent: f32) -> Transform {
+        let (x, y) = viewport.anchor(x_percent, y_percent);
+        Transform { x, y, depth: 0, scale: viewport.get_scale() }
+    }
+    /// Offset and scale an animation applies on top of an object's own transform.
+    pub fn posed(x: f32, y: f32, scale: f32) -> Transform {
+        Transform { x, y, depth: 0, scale }
+    }
+    /// Root that maps reference-resolution coordinates onto the window, letterboxed to keep proportions.
+    pub fn fitted(viewport: &Viewport) -> Transform {
+        let (x, y) = viewport.get_reference_origin();
+        Transform { x, y, depth: 0, scale: viewport.get_scale() }
+    }
+    /// Turns this parent-relative transform into one in the parent's space.
+    pub fn compose(&self, parent: &Transform) -> Transform {
+        Transform {
+            x: parent.x+self.x*parent.scale,
+            y: parent.y+self.y*parent.scale,
+            depth: parent.depth+self.depth,
+            scale: parent.scale*self.scale
+        }
+    }
+    pub fn get_depth(&self) -> i32 {
+        self.depth
+    }
+    pub fn get_position(&self) -> Point2<f32> {
+        Point2 { x: self.x, y: self.y }
+    }
+    pub fn get_scale(&self) -> f32 {
+        self.scale
+    }
+    /// Turns a point in the space this transform lives in (e.g. window coordinates for a world transform)
+    /// into one relative to it.
+    pub fn to_local(self, point: Point2<f32>) -> Point2<f32> {
+        Point2 { x: (point.x-self.x)/self.scale, y: (point.y-self.y)/self.scale }
+    }
 }
 #[derive(Clone)]
 struct BoardState {
     hotbar_values: Vec<Option<Fraction>>,
     target_value: Option<Fraction>,
     workbench_left_value: Option<Fraction>,
+    workbench_left_expression: Option<String>,
     workbench_center_value: OpType,
-    workbench_right_value: Option<Fraction>
+    workbench_right_value: Option<Fraction>,
+    workbench_right_expression: Option<String>
+}
+
+/// A clickable place on the board.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BoardSlot {
+    Hotbar(usize),
+    Palette(usize), //Index into PALETTE_OPERATIONS
+    WorkbenchLeft,
+    WorkbenchOperation,
+    WorkbenchRight,
+    Target
+}
+
+//Operators offered as clickable tiles below the hotbar
+const PALETTE_OPERATIONS: &'static [OpType] = &[OpType::Plus, OpType::Minus, OpType::Multiply, OpType::Divide];
+
+/// A number or operator tile, as the board's object tree holds them.
+enum BoardPiece {
+    Number(VisibleNumber),
+    Operation(VisibleOperation)
+}
+impl BoardPiece {
+    fn get_tile(&self) -> &Tile {
+        match self {
+            BoardPiece::Number(number) => &number.tile,
+            BoardPiece::Operation(operation) => &operation.tile
+        }
+    }
+    fn get_tile_mut(&mut self) -> &mut Tile {
+        match self {
+            BoardPiece::Number(number) => &mut number.tile,
+            BoardPiece::Operation(operation) => &mut operation.tile
+        }
+    }
+}
+impl GameObject for BoardPiece {
+    fn get_depth(&self) -> i32 { self.get_tile().get_depth() }
+    fn update(&mut self, _delta: f32) {
+        self.get_tile_mut().update(_delta);
+    }
+    fn draw(&mut self, _canvas: &mut Canvas, _parent: &Transform) {
+        self.get_tile_mut().draw(_canvas, _parent);
+    }
 }
 
 struct VisibleBoard {
-    hotbar: Vec<Box<VisibleNumber>>,
-    target: VisibleNumber,
-    workbench_left: VisibleNumber,
-    workbench_right: VisibleNumber,
-    workbench_center: VisibleOperation,
+    transform: Transform, //Every tile is a child of this
+    pieces: ObjectTree<BoardPiece>, //Draws the tiles by depth, so a dragged one goes on top of the rest
+    hotbar: Vec<Handle<BoardPiece>>,
+    palette: Vec<Handle<BoardPiece>>,
+    target: Handle<BoardPiece>,
+    workbench_left: Handle<BoardPiece>,
+    workbench_center: Handle<BoardPiece>,
+    workbench_right: Handle<BoardPiece>
 }
 impl VisibleBoard {
     fn new(b: &Board, layout: &BoardLayout) -> VisibleBoard {
+        let mut pieces = ObjectTree::new();
         //hotbar
         let mut hotbar = Vec::new();
         for (item, pos) in b.input.iter().zip(layout.hotbar_pos_vec.iter()) {
             hotbar.push(
-                Box::new(
+                pieces.insert(Box::new(BoardPiece::Number(
                     VisibleNumber::new(
                         Some(Fraction::from(*item)), 
                         pos.x, 
                         pos.y, 
                         0, 
                         Color::WHITE
-                    )
-                )
+                    ).with_empty_state(TileState::Consumed)
+                )))
             )
         }
+        //operator palette
+        let mut palette = Vec::new();
+        for (op_type, pos) in PALETTE_OPERATIONS.iter().zip(layout.palette_pos_vec.iter()) {
+            let mut operation = VisibleOperation::new(pos.x, pos.y, 0, Color::WHITE);
+            operation.set_operation(op_type.clone());
+            palette.push(pieces.insert(Box::new(BoardPiece::Operation(operation))));
+        }
         //target
         let target = VisibleNumber::new(
             Some(Fraction::from(b.target)), 
@@ -75,9 +188,10 @@ impl VisibleBoard {
             0, 
             Color::RED
         );
+        let target = pieces.insert(Box::new(BoardPiece::Number(target)));
 
             //workbench
-            let first_pos = layout.workbench_pos_vec.get(0).expect("Could not get first position from workbench position vector.");
+            let first_pos = layout.workbench_pos_vec.first().expect("Could not get first position from workbench position vector.");
             let second_pos = layout.workbench_pos_vec.get(1).expect("Could not get second position from workbench position vector.");
             let third_pos = layout.workbench_pos_vec.get(2).expect("Could not get third position from workbench position vector.");
             
@@ -103,55 +217,147 @@ impl VisibleBoard {
             );
 
         VisibleBoard {
+            transform: Transform::origin(),
             hotbar,
+            palette,
             target,
-            workbench_left,
-            workbench_center,
-            workbench_right
+            workbench_left: pieces.insert(Box::new(BoardPiece::Number(workbench_left))),
+            workbench_center: pieces.insert(Box::new(BoardPiece::Operation(workbench_center))),
+            workbench_right: pieces.insert(Box::new(BoardPiece::Number(workbench_right))),
+            pieces
         }
     }
 
     fn load_board_state(&mut self, state: BoardState) {
-        for (item, val) in self.hotbar.iter_mut().zip(state.hotbar_values) {
-            item.update_value(val);
+        for (slot, val) in self.get_hotbar_slots().into_iter().zip(state.hotbar_values) {
+            self.get_number_mut(slot).expect("").update_value(val);
         }
 
-        self.target.value = state.target_value;
-        self.workbench_left.update_value(state.workbench_left_value);
-        self.workbench_right.update_value(state.workbench_right_value);
-        self.workbench_center.set_operation(state.workbench_center_value);
+        self.get_number_mut(BoardSlot::Target).expect("").value = state.target_value;
+        self.get_number_mut(BoardSlot::WorkbenchLeft).expect("").update_value_with_expression(state.workbench_left_value, state.workbench_left_expression);
+        self.get_number_mut(BoardSlot::WorkbenchRight).expect("").update_value_with_expression(state.workbench_right_value, state.workbench_right_expression);
+        self.get_operation_mut(BoardSlot::WorkbenchOperation).expect("").set_operation(state.workbench_center_value);
     }
 
     fn get_board_state(&self) -> BoardState {
         let mut hotbar_values = Vec::new();
-        for item in self.hotbar.iter() {
-            hotbar_values.push(item.value)
+        for slot in self.get_hotbar_slots() {
+            hotbar_values.push(self.get_value(slot))
         }
         BoardState {
             hotbar_values,
-            target_value: self.target.value,
-            workbench_left_value: self.workbench_left.value,
-            workbench_center_value: self.workbench_center.value.clone(),
-            workbench_right_value: self.workbench_right.value
+            target_value: self.get_value(BoardSlot::Target),
+            workbench_left_value: self.get_value(BoardSlot::WorkbenchLeft),
+            workbench_left_expression: self.get_number(BoardSlot::WorkbenchLeft).and_then(|n| n.expression.clone()),
+            workbench_center_value: self.get_operation_type(),
+            workbench_right_value: self.get_value(BoardSlot::WorkbenchRight),
+            workbench_right_expression: self.get_number(BoardSlot::WorkbenchRight).and_then(|n| n.expression.clone())
         }
     }
+    fn get_handle(&self, slot: BoardSlot) -> Option<Handle<BoardPiece>> {
+        match slot {
+            BoardSlot::Hotbar(i) => self.hotbar.get(i).copied(),
+            BoardSlot::Palette(i) => self.palette.get(i).copied(),
+            BoardSlot::WorkbenchLeft => Some(self.workbench_left),
+            BoardSlot::WorkbenchOperation => Some(self.workbench_center),
+            BoardSlot::WorkbenchRight => Some(self.workbench_right),
+            BoardSlot::Target => Some(self.target)
+        }
+    }
+    /// Every slot on the board, in the order the pointer checks them.
+    fn get_slots(&self) -> Vec<BoardSlot> {
+        let mut slots = self.get_hotbar_slots();
+        slots.extend((0..self.palette.len()).map(BoardSlot::Palette));
+        slots.extend([BoardSlot::WorkbenchLeft, BoardSlot::WorkbenchOperation, BoardSlot::WorkbenchRight, BoardSlot::Target]);
+        slots
+    }
+    fn get_hotbar_slots(&self) -> Vec<BoardSlot> {
+        (0..self.hotbar.len()).map(BoardSlot::Hotbar).collect()
+    }
+    /// None if there is no such slot or it holds an operator.
+    fn get_number(&self, slot: BoardSlot) -> Option<&VisibleNumber> {
+        match self.get_handle(slot).and_then(|h| self.pieces.get(h)) {
+            Some(BoardPiece::Number(number)) => Some(number),
+            _ => None
+        }
+    }
+    fn get_number_mut(&mut self, slot: BoardSlot) -> Option<&mut VisibleNumber> {
+        match self.get_handle(slot).and_then(|h| self.pieces.get_mut(h)) {
+            Some(BoardPiece::Number(number)) => Some(number),
+            _ => None
+        }
+    }
+    fn get_operation_mut(&mut self, slot: BoardSlot) -> Option<&mut VisibleOperation> {
+        match self.get_handle(slot).and_then(|h| self.pieces.get_mut(h)) {
+            Some(BoardPiece::Operation(operation)) => Some(operation),
+            _ => None
+        }
+    }
+    fn get_tile(&self, slot: BoardSlot) -> Option<&Tile> {
+        self.get_handle(slot).and_then(|h| self.pieces.get(h)).map(|piece| piece.get_tile())
+    }
+    fn get_tile_mut(&mut self, slot: BoardSlot) -> Option<&mut Tile> {
+        self.get_handle(slot).and_then(|h| self.pieces.get_mut(h)).map(|piece| piece.get_tile_mut())
+    }
+    fn get_value(&self, slot: BoardSlot) -> Option<Fraction> {
+        self.get_number(slot).and_then(|n| n.value)
+    }
+    /// The operator on the workbench.
+    fn get_operation_type(&self) -> OpType {
+        match self.pieces.get(self.workbench_center) {
+            Some(BoardPiece::Operation(operation)) => operation.value.clone(),
+            _ => OpType::None
+        }
+    }
+    /// Highlights the workbench slot the next number will go to.
+    fn refresh_selection(&mut self) {
+        let left_open = self.get_value(BoardSlot::WorkbenchLeft).is_none();
+        let right_open = self.get_value(BoardSlot::WorkbenchRight).is_none();
+        self.get_number_mut(BoardSlot::WorkbenchLeft).expect("").set_selected(left_open);
+        self.get_number_mut(BoardSlot::WorkbenchRight).expect("").set_selected(!left_open && right_open);
+    }
+    fn get_slot_at(&self, point: Point2<f32>, parent: &Transform) -> Option<BoardSlot> {
+        let world = self.transform.compose(parent);
+        self.get_slots().into_iter().find(|&slot| self.get_tile(slot).is_some_and(|tile| tile.contains_point(point, &world)))
+    }
+    /// Makes a hotbar or palette tile follow the pointer, at a point relative to the board. None puts it back.
+    fn set_drag_position(&mut self, slot: BoardSlot, position: Option<Point2<f32>>) {
+        match slot {
+            BoardSlot::Hotbar(_) => {
+                if let Some(item) = self.get_number_mut(slot) {
+                    item.tile.set_drag_position(position);
+                    item.set_selected(position.is_some());
+                }
+            },
+            BoardSlot::Palette(_) => {
+                if let Some(tile) = self.get_tile_mut(slot) {
+                    tile.set_drag_position(position);
+                }
+            },
+            _ => {}
+        }
+    }
+    fn is_animating(&self) -> bool {
+        self.get_slots().into_iter().any(|slot| self.get_tile(slot).is_some_and(|tile| tile.is_animating()))
+    }
 }
 impl GameObject for VisibleBoard {
-    fn draw(&mut self, _canvas: &mut Canvas) {
-        for item in self.hotbar.as_mut_slice() {
-            item.draw(_canvas);
-        }
-        self.target.draw(_canvas);
-        self.workbench_left.draw(_canvas);
-        self.workbench_center.draw(_canvas);
-        self.workbench_right.draw(_canvas);
+    fn get_transform(&self) -> Transform { self.transform }
+    fn update(&mut self, _delta: f32) {
+        self.refresh_selection();
+        self.pieces.update(_delta);
+    }
+    fn draw(&mut self, _canvas: &mut Canvas, _parent: &Transform) {
+        let world = self.transform.compose(_parent);
+        self.pieces.draw(_canvas, &world);
     }
 }
 
 pub struct BoardContainer {
     pool_map: HashMap<String, DifficultyPools>,
     config: SetConfig,
-    sequence: Queue<Board>
+    sequence: Queue<Board>,
+    rng: StdRng
 }
 impl BoardContainer {
     pub fn new(pool_map: HashMap<String, DifficultyPools>, config: SetConfig) -> BoardContainer {
@@ -159,65 +365,245 @@ impl BoardContainer {
             pool_map,
             config,
             sequence: Queue::new(),
+            rng: StdRng::from_entropy()
         }
     }
-    pub fn generate_new_board_sequence(&mut self) {
-        match data_generator::get_set_of_inputs(self.pool_map.clone(), &self.config) {
-            Ok(res) => {
-                for board in res {
-                    self.sequence.add(board).expect("Unable to add board to queue (in BoardContainer)");
-                }
-            },
-            Err(_) => {}
+    /// Deals the same boards in the same order every time it is given the same seed, pools and config.
+    pub fn with_seed(pool_map: HashMap<String, DifficultyPools>, config: SetConfig, seed: u64) -> BoardContainer {
+        let mut container = BoardContainer::new(pool_map, config);
+        container.rng = StdRng::seed_from_u64(seed);
+        container
+    }
+    /// Queues a freshly dealt set. Fails, leaving the queue as it was, if the pools can't make a set to the config.
+    pub fn generate_new_board_sequence(&mut self) -> Result<(), String> {
+        let res = data_generator::get_set_of_inputs(self.pool_map.clone(), &self.config, &mut self.rng)?;
+        for board in res {
+            self.sequence.add(board).expect("Unable to add board to queue (in BoardContainer)");
         }
+        Ok(())
     }
     pub fn get_next_board(&mut self) -> Option<Board> {
-        match self.sequence.remove() {
-            Ok(b) => {Some(b)},
-            Err(_) => None
+        self.sequence.remove().ok()
+    }
+    pub fn clear_sequence(&mut self) {
+        self.sequence = Queue::new();
+    }
+    pub fn get_difficulties(&self) -> Vec<InputDifficulty> {
+        self.config.get_difficulties().clone()
+    }
+    /// Swaps the difficulties boards are drawn from and refills the queue with boards of the new difficulties.
+    /// If no set can be dealt at the new difficulties, the old ones and the queue are kept.
+    pub fn set_difficulties(&mut self, difficulties: Vec<InputDifficulty>) -> Result<(), String> {
+        let previous = self.config.get_difficulties().clone();
+        let sequence = std::mem::replace(&mut self.sequence, Queue::new());
+        self.config.set_difficulties(difficulties);
+        if let Err(e) = self.generate_new_board_sequence() {
+            self.config.set_difficulties(previous);
+            self.sequence = sequence;
+            return Err(e);
         }
+        Ok(())
     }
 }
 
+const MOVE_ANIMATION_SECONDS: f32 = 0.2;
+const POP_ANIMATION_SECONDS: f32 = 0.25;
+const PULSE_ANIMATION_SECONDS: f32 = 0.5;
+const DRAG_THRESHOLD: f32 = 6.0; //How far the pointer moves, in window pixels, before a press becomes a drag
+
+/// Something the player does to the board, whichever device it came from.
+#[derive(Clone, Debug, PartialEq)]
+enum BoardAction {
+    Compute,
+    MoveToWorkbench(usize), //Hotbar index, into the first open workbench slot
+    DropOnWorkbench(usize, BoardSlot, Point2<f32>), //Hotbar index dropped on a slot, at a point relative to the board
+    SetOperation(OpType),
+    Undo,
+    Hint,
+    Skip
+}
+//What each semantic does to the board
+const BOARD_ACTIONS: &'static [(InputSemantic, BoardAction)] = &[
+    (InputSemantic::Accept, BoardAction::Compute),
+    (InputSemantic::Hotbar1, BoardAction::MoveToWorkbench(0)),
+    (InputSemantic::Hotbar2, BoardAction::MoveToWorkbench(1)),
+    (InputSemantic::Hotbar3, BoardAction::MoveToWorkbench(2)),
+    (InputSemantic::Hotbar4, BoardAction::MoveToWorkbench(3)),
+    (InputSemantic::Plus, BoardAction::SetOperation(OpType::Plus)),
+    (InputSemantic::Minus, BoardAction::SetOperation(OpType::Minus)),
+    (InputSemantic::Multiply, BoardAction::SetOperation(OpType::Multiply)),
+    (InputSemantic::Divide, BoardAction::SetOperation(OpType::Divide)),
+    (InputSemantic::Back, BoardAction::Undo),
+    (InputSemantic::Hint, BoardAction::Hint),
+    (InputSemantic::Skip, BoardAction::Skip)
+];
+
+/// A press on a board tile that hasn't been released yet.
+struct PointerDrag {
+    source: BoardSlot,
+    start: Point2<f32>, //Window coordinates
+    dragging: bool //Moved far enough to count as a drag rather than a click
+}
+
 pub struct GameController {
     board: BoardContainer,
-    xpos: f32,
-    ypos: f32,
+    transform: Transform, //Center of the board; every child below is positioned relative to it
     x_spacing: f32,
     visible_board: Option<VisibleBoard>,
+    current_board: Option<Board>,
+    expression_entry: Option<VisibleExpression>,
+    hint_text: RenderText,
+    score: ScoreKeeper,
+    score_display: ScoreDisplay,
+    board_elapsed: f32,
+    undo_count: u32,
+    hint_count: u32,
+    hints_enabled: bool,
+    mode: GameMode,
+    round_timer: Option<Countdown>,
+    board_timer: Option<Countdown>,
+    lives: Option<u32>,
+    difficulty_tier: Option<usize>,
+    status_text: RenderText,
+    round_over: bool,
+    practice_result: Option<PracticeResult>,
     seq_initialized: bool,
-    history: Vec<BoardState>
+    history: Vec<BoardState>,
+    animation_input: AnimationInputPolicy,
+    queued_actions: VecDeque<BoardAction>, //Held back while the board animates
+    drag: Option<PointerDrag>,
+    pending_solve: Option<String> //Expression of a solved board, kept until its victory animation finishes
 }
 impl GameController {
-    pub fn new(board: BoardContainer) -> GameController {
+    pub fn new(board: BoardContainer, mode: GameMode) -> GameController {
         GameController {
             board,
-            xpos: 480.0,//TODO: pass in center coordinates externally
-            ypos: 270.0,
+            transform: Transform::anchored(&Viewport::reference(), 0.5, 0.5),
             x_spacing: 20.0,
             visible_board: None,
+            current_board: None,
+            expression_entry: None,
+            hint_text: RenderText::new(0.0, 190.0, 0, "", Color::YELLOW),
+            score: ScoreKeeper::new(),
+            score_display: ScoreDisplay::new(200.0, -40.0, 0),
+            board_elapsed: 0.0,
+            undo_count: 0,
+            hint_count: 0,
+            hints_enabled: true,
+            mode,
+            round_timer: mode.get_round_duration().map(Countdown::new),
+            board_timer: mode.get_board_duration().map(Countdown::new),
+            lives: mode.get_lives(),
+            difficulty_tier: None,
+            status_text: RenderText::new(0.0, -100.0, 0, "", Color::WHITE),
+            round_over: false,
+            practice_result: None,
             seq_initialized: false,
-            history: Vec::new()
+            history: Vec::new(),
+            animation_input: AnimationInputPolicy::Queue,
+            queued_actions: VecDeque::new(),
+            drag: None,
+            pending_solve: None
         }
     }
+    /// Deals the next board, refilling the queue first if the mode keeps going after a set runs out.
+    /// Ends the round if there is nothing left to deal.
     fn load_board(&mut self) {
         self.visible_board = None;
-        match self.board.get_next_board() {
+        let next = match self.board.get_next_board() {
+            None if !self.mode.ends_with_sequence() => {
+                match self.board.generate_new_board_sequence() {
+                    Ok(()) => self.board.get_next_board(),
+                    Err(e) => {
+                        error!("Could not deal a new set of boards: {}", e);
+                        None
+                    }
+                }
+            },
+            next => next
+        };
+        match next {
             Some(b) => {
                 //NOTE: Game Controller is too generic for this. We should construct a board layout manager that has specifically hotbar, target, and workbench fields of fixed size and type.
-                let layout = BoardLayout::new(self.xpos, self.ypos, self.x_spacing, b.input.len()); 
+                let layout = BoardLayout::new(0.0, 0.0, self.x_spacing, b.input.len()); 
                 
                 self.visible_board = Some(VisibleBoard::new(&b, &layout));
+                self.current_board = Some(b);
                 self.history.clear();
+                self.queued_actions.clear();
+                self.drag = None;
+                self.board_elapsed = 0.0;
+                self.undo_count = 0;
+                self.hint_count = 0;
+                self.hint_text.set_text("".to_string());
+                if let Some(entry) = &mut self.expression_entry {
+                    entry.clear();
+                }
+                if let Some(timer) = &mut self.board_timer {
+                    timer.reset();
+                }
             },
-            None => { self.reinitialize(); }
+            None => { self.end_round(); }
+        }
+    }
+    /// Puts the current board back the way it was dealt. Clocks keep running.
+    /// A solve whose victory pulse is still playing is called off, so the board stays rather than advancing.
+    pub fn restart_board(&mut self) {
+        if let Some(b) = &self.current_board {
+            let layout = BoardLayout::new(0.0, 0.0, self.x_spacing, b.input.len());
+            self.visible_board = Some(VisibleBoard::new(b, &layout));
+            self.history.clear();
+            self.queued_actions.clear();
+            self.drag = None;
+            self.pending_solve = None;
+            self.hint_text.set_text("".to_string());
+            if let Some(entry) = &mut self.expression_entry {
+                entry.clear();
+            }
+        }
+    }
+    pub fn get_settings(&self) -> GameSettings {
+        GameSettings {
+            difficulties: self.board.get_difficulties(),
+            hints_enabled: self.hints_enabled,
+            animation_input: self.animation_input
+        }
+    }
+    /// The board tile under a point in window coordinates, if any.
+    pub fn get_slot_at(&self, x: f32, y: f32) -> Option<BoardSlot> {
+        if self.practice_result.is_some() { return None; } //The board isn't drawn under the result panel
+        match &self.visible_board {
+            Some(vb) => vb.get_slot_at(Point2 { x, y }, &self.transform),
+            None => None
+        }
+    }
+    /// Keeps the board centered in the window and scaled to fit it.
+    pub fn set_viewport(&mut self, viewport: &Viewport) {
+        self.transform = Transform::anchored(viewport, 0.5, 0.5);
+    }
+    pub fn apply_settings(&mut self, settings: GameSettings) {
+        self.hints_enabled = settings.hints_enabled;
+        self.animation_input = settings.animation_input;
+        if !self.hints_enabled {
+            self.hint_text.set_text("".to_string());
+        }
+        //Survival picks its own difficulties as the streak grows
+        if self.lives.is_none() && settings.difficulties != self.board.get_difficulties() {
+            if let Err(e) = self.board.set_difficulties(settings.difficulties) {
+                warn!("Keeping the previous difficulties: {}", e);
+            }
         }
     }
-    fn get_open_workbench_slot(&mut self) -> Option<&mut VisibleNumber> {
-        if self.visible_board.is_some() {
-            let vb = self.visible_board.as_mut().expect("");
-            if vb.workbench_left.value.is_none() { return Some(&mut vb.workbench_left); }
-            else if vb.workbench_right.value.is_none() { return Some(&mut vb.workbench_right); }
+    /// `destination` if it is an empty workbench slot, or the first empty one if None.
+    fn get_open_workbench_slot(&mut self, destination: Option<BoardSlot>) -> Option<&mut VisibleNumber> {
+        if let Some(vb) = &mut self.visible_board {
+            let slot = match destination {
+                None if vb.get_value(BoardSlot::WorkbenchLeft).is_none() => BoardSlot::WorkbenchLeft,
+                None => BoardSlot::WorkbenchRight,
+                Some(slot @ (BoardSlot::WorkbenchLeft | BoardSlot::WorkbenchRight)) => slot,
+                _ => { return None; }
+            };
+            return vb.get_number_mut(slot).filter(|number| number.value.is_none());
         }
         None
     }
@@ -226,29 +612,43 @@ impl GameController {
         let mut success = true;
         let mut result: Option<Fraction> = None;
         let mut target: Option<Fraction> = None;
+        let mut expression = String::new();
         let mut all_values_exhausted = false;
-        if self.visible_board.is_some() {
-            let vb = self.visible_board.as_mut().expect("");
-            if vb.workbench_left.value.is_some() && 
-                vb.workbench_right.value.is_some() &&
-                vb.workbench_center.value != OpType::None
+        if let Some(vb) = &mut self.visible_board {
+            let op_type = vb.get_operation_type();
+            if vb.get_value(BoardSlot::WorkbenchLeft).is_some() && 
+                vb.get_value(BoardSlot::WorkbenchRight).is_some() &&
+                op_type != OpType::None
             {
-                let mut val = None;
-                match vb.workbench_center.value {
-                    OpType::Plus => val = Some(vb.workbench_left.value.expect("") + vb.workbench_right.value.expect("")),
-                    OpType::Minus => val = Some(vb.workbench_left.value.expect("") - vb.workbench_right.value.expect("")),
-                    OpType::Multiply => val = Some(vb.workbench_left.value.expect("") * vb.workbench_right.value.expect("")),
-                    OpType::Divide => val = Some(vb.workbench_left.value.expect("") / vb.workbench_right.value.expect("")),
-                    _ => {success = false;}
+                let val = op_type.apply(vb.get_value(BoardSlot::WorkbenchLeft).expect(""), vb.get_value(BoardSlot::WorkbenchRight).expect(""));
+                if val.is_none() {
+                    success = false;
+                    for slot in [BoardSlot::WorkbenchLeft, BoardSlot::WorkbenchOperation, BoardSlot::WorkbenchRight] {
+                        vb.get_tile_mut(slot).expect("").flash_error();
+                    }
+                }
+                expression = format!(
+                    "({} {} {})",
+                    vb.get_number(BoardSlot::WorkbenchLeft).expect("").get_expression(),
+                    op_type.symbol(),
+                    vb.get_number(BoardSlot::WorkbenchRight).expect("").get_expression()
+                );
+                let workbench_left = vb.get_number_mut(BoardSlot::WorkbenchLeft).expect("");
+                workbench_left.update_value_with_expression(val, Some(expression.clone()));
+                if val.is_some() {
+                    workbench_left.play_animation(
+                        Animation::new(Pose::rest().with_scale(0.0).with_alpha(0.0))
+                            .then(Pose::rest().with_scale(1.3), POP_ANIMATION_SECONDS*0.6, Easing::EaseOut)
+                            .then(Pose::rest(), POP_ANIMATION_SECONDS*0.4, Easing::EaseIn)
+                    );
                 }
-                vb.workbench_left.update_value(val);
-                vb.workbench_right.update_value(None);
-                vb.workbench_center.set_operation(OpType::None);
-                result = vb.workbench_left.value;
-                target = vb.target.value;
+                vb.get_number_mut(BoardSlot::WorkbenchRight).expect("").update_value(None);
+                vb.get_operation_mut(BoardSlot::WorkbenchOperation).expect("").set_operation(OpType::None);
+                result = vb.get_value(BoardSlot::WorkbenchLeft);
+                target = vb.get_value(BoardSlot::Target);
                 all_values_exhausted = true;
-                for item in vb.hotbar.iter() {
-                    if item.value != None {
+                for slot in vb.get_hotbar_slots() {
+                    if vb.get_value(slot).is_some() {
                         all_values_exhausted = false;
                     }
                 }
@@ -259,21 +659,212 @@ impl GameController {
         }
         else {
             if result == target && all_values_exhausted {
-                //Win
-                println!("Value {:?} was successfully reached! (Victory)", target);
-                self.load_board(); //Infinite gameplay!
+                self.on_board_solved(solver::strip_outer_parens(&expression));
+            }
+        }
+    }
+    /// `expression` is the player's route to the target, written out in full.
+    fn on_board_solved(&mut self, expression: String) {
+        let target = self.visible_board.as_ref().and_then(|vb| vb.get_value(BoardSlot::Target));
+        info!("Value {:?} was successfully reached! (Victory)", target);
+        if let Some(b) = &self.current_board {
+            self.score.record_solve(&SolveRecord {
+                difficulty: b.difficulty,
+                seconds: self.board_elapsed,
+                undos: self.undo_count,
+                hints: self.hint_count
+            });
+            self.score_display.refresh(&self.score);
+        }
+        if let Some(vb) = &mut self.visible_board {
+            vb.get_number_mut(BoardSlot::Target).expect("").play_animation(
+                Animation::new(Pose::rest())
+                    .then(Pose::rest().with_scale(1.5).with_color([0.4, 1.0, 0.4, 1.0]), PULSE_ANIMATION_SECONDS/2.0, Easing::EaseOut)
+                    .then(Pose::rest(), PULSE_ANIMATION_SECONDS/2.0, Easing::EaseInOut)
+            );
+        }
+        self.pending_solve = Some(expression); //Finished in update once the pulse is over
+    }
+    fn finish_solve(&mut self, expression: String) {
+        self.queued_actions.clear(); //Whatever was held back was meant for the board that just ended
+        if self.mode == GameMode::Practice {
+            self.show_practice_result(expression);
+            return; //The player moves on to the next board from the result panel
+        }
+        self.advance_board(); //Infinite gameplay!
+    }
+    fn is_animating(&self) -> bool {
+        match &self.visible_board {
+            Some(vb) => vb.is_animating(),
+            None => false
+        }
+    }
+    fn show_practice_result(&mut self, expression: String) {
+        if let Some(b) = &self.current_board {
+            let solutions = solver::find_solutions_for_input(&b.input, b.target);
+            self.practice_result = Some(PracticeResult::new(0.0, -60.0, 0, &expression, &solutions));
+        }
+    }
+    fn close_practice_result(&mut self) {
+        self.practice_result = None;
+        self.load_board();
+    }
+    fn forfeit_board(&mut self) {
+        info!("Board forfeited.");
+        self.score.record_forfeit();
+        self.score_display.refresh(&self.score);
+        if let Some(lives) = &mut self.lives {
+            *lives = lives.saturating_sub(1);
+            if *lives == 0 {
+                self.end_round();
+                return;
+            }
+        }
+        self.advance_board();
+    }
+    fn advance_board(&mut self) {
+        match self.update_difficulty_tier() {
+            Ok(_) => { self.load_board(); },
+            Err(e) => {
+                error!("Could not deal boards for the next difficulty tier: {}", e);
+                self.end_round();
             }
         }
     }
+    /// In survival, moves the board queue to the difficulty tier matching the current streak.
+    /// Returns true if the queue was refilled.
+    fn update_difficulty_tier(&mut self) -> Result<bool, String> {
+        if self.lives.is_none() { return Ok(false); }
+        let tier = game_mode::get_survival_tier(self.score.get_streak());
+        if self.difficulty_tier == Some(tier) { return Ok(false); }
+        self.difficulty_tier = Some(tier);
+        self.board.set_difficulties(game_mode::get_survival_tier_difficulties(tier))?;
+        return Ok(true);
+    }
+    fn end_round(&mut self) {
+        self.round_over = true;
+    }
+    pub fn is_round_over(&self) -> bool {
+        self.round_over
+    }
+    pub fn build_round_summary(&self) -> RoundSummary {
+        //Positioned relative to the center of the window, like the board
+        RoundSummary::new(0.0, -60.0, 0, &self.mode, &self.score)
+    }
+    pub fn restart_round(&mut self) {
+        self.round_over = false;
+        self.score.reset();
+        self.score_display.refresh(&self.score);
+        if let Some(timer) = &mut self.round_timer {
+            timer.reset();
+        }
+        self.lives = self.mode.get_lives();
+        self.difficulty_tier = None;
+        self.board.clear_sequence();
+        if let Err(e) = self.start_round() {
+            error!("Could not restart the round: {}", e);
+            self.end_round();
+        }
+    }
+    /// Deals the round's first set and its first board. Fails if the config can't produce a set,
+    /// in which case there is nothing to play.
+    pub fn start_round(&mut self) -> Result<(), String> {
+        self.seq_initialized = true;
+        if !self.update_difficulty_tier()? {
+            self.board.generate_new_board_sequence()?;
+        }
+        self.load_board();
+        Ok(())
+    }
+    fn refresh_status_text(&mut self) {
+        let mut segments = Vec::new();
+        if let Some(timer) = &self.round_timer {
+            segments.push(format!("Time: {}", timer::format_seconds(timer.get_remaining())));
+        }
+        if let Some(timer) = &self.board_timer {
+            segments.push(format!("Board: {}", timer::format_seconds(timer.get_remaining())));
+        }
+        if let Some(lives) = self.lives {
+            segments.push(format!("Lives: {}", lives));
+        }
+        if let Some(tier) = self.difficulty_tier {
+            segments.push(format!("Tier: {}", tier+1));
+        }
+        self.status_text.set_text(segments.join("   "));
+    }
+    fn show_hint(&mut self) {
+        let vb = match &self.visible_board {
+            Some(vb) => vb,
+            None => { return; }
+        };
+        let target = match vb.get_value(BoardSlot::Target) {
+            Some(t) => t,
+            None => { return; }
+        };
+        let mut remaining = Vec::new();
+        for slot in vb.get_hotbar_slots() {
+            if let Some(val) = vb.get_value(slot) { remaining.push(val); }
+        }
+        if let Some(val) = vb.get_value(BoardSlot::WorkbenchLeft) { remaining.push(val); }
+        if let Some(val) = vb.get_value(BoardSlot::WorkbenchRight) { remaining.push(val); }
+        match solver::find_first_solution(&remaining, target).and_then(|sol| sol.steps.first().cloned()) {
+            Some(step) => {
+                self.hint_text.set_text(format!("Hint: {}", step));
+                self.hint_count += 1; //Only hints that gave something away count against the score
+            },
+            None => { self.hint_text.set_text("Hint: no solution from here. Try undoing.".to_string()); }
+        }
+    }
+    fn toggle_expression_entry(&mut self) {
+        if self.expression_entry.is_some() {
+            self.expression_entry = None;
+        }
+        else {
+            self.expression_entry = Some(VisibleExpression::new(0.0, 130.0, 0, Color::WHITE));
+        }
+    }
+    fn try_submit_expression(&mut self) {
+        let (inputs, target) = match &self.current_board {
+            Some(b) => (b.input.clone(), b.target),
+            None => { return; }
+        };
+        let entry = match &mut self.expression_entry {
+            Some(entry) => entry,
+            None => { return; }
+        };
+        match expression_parser::evaluate_with_inputs(&entry.get_text(), &inputs) {
+            Ok(val) => {
+                if val == target {
+                    let expression = entry.get_text().trim().to_string();
+                    entry.clear();
+                    self.on_board_solved(expression);
+                }
+                else {
+                    entry.set_error(Some(format!("Expression equals {}, not {}", val, target)));
+                }
+            },
+            Err(e) => { entry.set_error(Some(e.to_string())); }
+        }
+    }
     fn try_move_number_to_workbench(&mut self, index: usize) -> bool {
+        self.try_move_number(index, None, None)
+    }
+    /// Moves a hotbar number into `destination`, or the first open workbench slot if None. It flies in from
+    /// `from` (relative to the board) if given, otherwise from its hotbar tile.
+    fn try_move_number(&mut self, index: usize, destination: Option<BoardSlot>, from: Option<Point2<f32>>) -> bool {
         self.history.push(self.visible_board.as_ref().expect("").get_board_state());
         let mut value_updated = false;
         if let Some(vb) = &mut self.visible_board {
-            if let Some(num) = vb.hotbar.get_mut(index) {
-                if num.value != None {
-                    let num_value = num.value.expect("");
-                    if let Some(slot) = self.get_open_workbench_slot() {
+            if let Some(num) = vb.get_number_mut(BoardSlot::Hotbar(index)) {
+                if let Some(num_value) = num.value {
+                    let from = from.unwrap_or(num.get_position());
+                    if let Some(slot) = self.get_open_workbench_slot(destination) {
                         slot.update_value(Some(num_value));
+                        let to = slot.get_position();
+                        slot.play_animation(
+                            Animation::new(Pose::offset(from.x-to.x, from.y-to.y))
+                                .then(Pose::rest(), MOVE_ANIMATION_SECONDS, Easing::EaseOutCubic)
+                        );
                         value_updated = true;
                     }
                 }
@@ -281,10 +872,15 @@ impl GameController {
         }
         if !value_updated {
             self.history.truncate(self.history.len().saturating_sub(1)); //Delete newly-added board state
+            if let Some(vb) = &mut self.visible_board {
+                if let Some(num) = vb.get_number_mut(BoardSlot::Hotbar(index)) {
+                    if num.value.is_some() { num.flash_error(); } //Nowhere to put it
+                }
+            }
         }
         else {
             if let Some(vb) = &mut self.visible_board {
-                if let Some(num) = vb.hotbar.get_mut(index) {
+                if let Some(num) = vb.get_number_mut(BoardSlot::Hotbar(index)) {
                     num.update_value(None);
                 }
             }
@@ -294,65 +890,189 @@ impl GameController {
     fn set_visible_operation(&mut self, op_type: OpType) {
         self.history.push(self.visible_board.as_ref().expect("").get_board_state());
         if let Some(vb) = &mut self.visible_board {
-            vb.workbench_center.set_operation(op_type);
+            vb.get_operation_mut(BoardSlot::WorkbenchOperation).expect("").set_operation(op_type);
         }
     }
     fn undo_last_action(&mut self) {
         if self.history.is_empty() { return; } //Does nothing if no history.
+        self.undo_count += 1;
         self.visible_board.as_mut().expect("").load_board_state(self.history.last().expect("").clone()); //Read previous board state from history
         self.history.truncate(self.history.len().saturating_sub(1)); //Delete previous board state 
     }
-    fn reinitialize(&mut self) {
-        self.board.generate_new_board_sequence();
-        self.load_board();
+    /// Runs the action now, or holds it back according to `animation_input` if the board is animating.
+    fn queue_board_action(&mut self, action: BoardAction) {
+        if !self.is_animating() && self.queued_actions.is_empty() {
+            self.perform_board_action(action);
+            return;
+        }
+        match self.animation_input {
+            AnimationInputPolicy::Block => {},
+            AnimationInputPolicy::Queue => { self.queued_actions.push_back(action); }
+        }
+    }
+    fn perform_board_action(&mut self, action: BoardAction) {
+        match action {
+            BoardAction::Compute => { self.try_compute(); },
+            BoardAction::MoveToWorkbench(index) => { self.try_move_number_to_workbench(index); },
+            BoardAction::DropOnWorkbench(index, slot, from) => { self.try_move_number(index, Some(slot), Some(from)); },
+            BoardAction::SetOperation(op_type) => { self.set_visible_operation(op_type); },
+            BoardAction::Undo => { self.undo_last_action(); },
+            BoardAction::Hint => { self.show_hint(); },
+            BoardAction::Skip => { self.forfeit_board(); }
+        }
+    }
+    /// Clicking a tile does what its key would; dragging a hotbar number or an operator onto the workbench places it there.
+    fn process_pointer(&mut self, input_manager: &InputManager) {
+        let cursor = input_manager.get_cursor_position();
+        match input_manager.get_input_state(InputSemantic::Pointer) {
+            InputState::Pressed => {
+                self.drag = self.get_slot_at(cursor.x, cursor.y).map(|source| PointerDrag { source, start: cursor, dragging: false });
+            },
+            InputState::Held => {
+                let local = self.to_board_local(cursor);
+                if let (Some(drag), Some(vb)) = (&mut self.drag, &mut self.visible_board) {
+                    let draggable = match drag.source {
+                        BoardSlot::Hotbar(_) => vb.get_value(drag.source).is_some(),
+                        BoardSlot::Palette(_) => true,
+                        _ => false
+                    };
+                    let distance = ((cursor.x-drag.start.x).powi(2)+(cursor.y-drag.start.y).powi(2)).sqrt();
+                    if draggable && distance > DRAG_THRESHOLD { drag.dragging = true; }
+                    if drag.dragging { vb.set_drag_position(drag.source, Some(local)); }
+                }
+            },
+            InputState::Released => {
+                if let Some(drag) = self.drag.take() {
+                    if let Some(vb) = &mut self.visible_board {
+                        vb.set_drag_position(drag.source, None);
+                    }
+                    let target = self.get_slot_at(cursor.x, cursor.y);
+                    let action = match (drag.dragging, drag.source, target) {
+                        (false, source, Some(target)) if source == target => self.get_click_action(source),
+                        (true, BoardSlot::Hotbar(i), Some(slot @ (BoardSlot::WorkbenchLeft | BoardSlot::WorkbenchRight))) => {
+                            Some(BoardAction::DropOnWorkbench(i, slot, self.to_board_local(cursor)))
+                        },
+                        (true, BoardSlot::Palette(i), Some(BoardSlot::WorkbenchOperation)) => {
+                            PALETTE_OPERATIONS.get(i).map(|op_type| BoardAction::SetOperation(op_type.clone()))
+                        },
+                        _ => None
+                    };
+                    if let Some(action) = action {
+                        self.queue_board_action(action);
+                    }
+                }
+            },
+            InputState::AtRest => {}
+        }
+    }
+    fn get_click_action(&self, slot: BoardSlot) -> Option<BoardAction> {
+        match slot {
+            BoardSlot::Hotbar(i) => Some(BoardAction::MoveToWorkbench(i)),
+            BoardSlot::Palette(i) => PALETTE_OPERATIONS.get(i).map(|op_type| BoardAction::SetOperation(op_type.clone())),
+            BoardSlot::WorkbenchOperation => Some(BoardAction::Compute), //The operator reads as "apply"
+            _ => None
+        }
+    }
+    fn to_board_local(&self, point: Point2<f32>) -> Point2<f32> {
+        match &self.visible_board {
+            Some(vb) => vb.transform.compose(&self.transform).to_local(point),
+            None => point
+        }
     }
 }
 impl GameObject for GameController {
-    fn update(&mut self) {
+    fn update(&mut self, _delta: f32) {
         if !self.seq_initialized {
-            self.reinitialize();
-            self.seq_initialized = true;
+            if let Err(e) = self.start_round() {
+                error!("Could not start the round: {}", e);
+                self.end_round();
+            }
+        }
+        if let Some(vb) = &mut self.visible_board {
+            vb.update(_delta);
         }
+        if !self.is_animating() {
+            if let Some(expression) = self.pending_solve.take() {
+                self.finish_solve(expression);
+            }
+            while !self.is_animating() {
+                match self.queued_actions.pop_front() {
+                    Some(action) => { self.perform_board_action(action); },
+                    None => { break; }
+                }
+            }
+        }
+        if self.round_over || self.practice_result.is_some() || self.pending_solve.is_some() { return; } //Clocks stop while a result is on screen
+        self.board_elapsed += _delta;
+        let mut round_expired = false;
+        let mut board_expired = false;
+        if let Some(timer) = &mut self.round_timer {
+            round_expired = timer.tick(_delta);
+        }
+        if let Some(timer) = &mut self.board_timer {
+            board_expired = timer.tick(_delta);
+        }
+        if round_expired { self.end_round(); }
+        else if board_expired { self.forfeit_board(); }
+        self.refresh_status_text();
     }
     fn get_depth(&self) -> i32 { return 0; }
-    fn draw(&mut self, _canvas: &mut Canvas) {
-        if self.visible_board.is_some() {
-            self.visible_board.as_mut().expect("Visible board was unexpectedly None (in GameController)").draw(_canvas);
+    fn get_transform(&self) -> Transform { self.transform }
+    fn draw(&mut self, _canvas: &mut Canvas, _parent: &Transform) {
+        let world = self.transform.compose(_parent);
+        if let Some(result) = &mut self.practice_result {
+            result.draw(_canvas, &world);
+            return;
+        }
+        self.status_text.draw(_canvas, &world);
+        if let Some(vb) = &mut self.visible_board {
+            vb.draw(_canvas, &world);
         }
+        if let Some(entry) = &mut self.expression_entry {
+            entry.draw(_canvas, &world);
+        }
+        self.hint_text.draw(_canvas, &world);
+        self.score_display.draw(_canvas, &world);
     }
 }
 impl ControllableGameObject for GameController {
     fn process_input(&mut self, _input_manager: &InputManager) {
-        if _input_manager.get_input_state(InputSemantic::Accept) == InputState::Pressed {
-            self.try_compute();
-        }
-            
-        if _input_manager.get_input_state(InputSemantic::Hotbar1) == InputState::Pressed {
-            self.try_move_number_to_workbench(0);
-        }
-        if _input_manager.get_input_state(InputSemantic::Hotbar2) == InputState::Pressed {
-            self.try_move_number_to_workbench(1);
-        }
-        if _input_manager.get_input_state(InputSemantic::Hotbar3) == InputState::Pressed {
-            self.try_move_number_to_workbench(2);
-        }
-        if _input_manager.get_input_state(InputSemantic::Hotbar4) == InputState::Pressed {
-            self.try_move_number_to_workbench(3);
-        }
-        if _input_manager.get_input_state(InputSemantic::Plus) == InputState::Pressed {
-            self.set_visible_operation(OpType::Plus);
+        if self.round_over || self.pending_solve.is_some() { return; }
+        if self.practice_result.is_some() {
+            if _input_manager.was_pressed_this_frame(InputSemantic::Accept) ||
+                _input_manager.was_pressed_this_frame(InputSemantic::Pointer)
+            {
+                self.close_practice_result();
+            }
+            return;
         }
-        if _input_manager.get_input_state(InputSemantic::Minus) == InputState::Pressed {
-            self.set_visible_operation(OpType::Minus);
+        if _input_manager.was_pressed_this_frame(InputSemantic::ToggleExpressionEntry) {
+            self.toggle_expression_entry();
         }
-        if _input_manager.get_input_state(InputSemantic::Multiply) == InputState::Pressed {
-            self.set_visible_operation(OpType::Multiply);
+        if self.expression_entry.is_some() {
+            //Editing arrives through process_text_input; only submission is semantic here.
+            if _input_manager.was_pressed_this_frame(InputSemantic::Accept) {
+                self.try_submit_expression();
+            }
+            return;
         }
-        if _input_manager.get_input_state(InputSemantic::Divide) == InputState::Pressed {
-            self.set_visible_operation(OpType::Divide);
+        self.process_pointer(_input_manager);
+        //Presses in the order they arrived, then anything held long enough to repeat
+        let mut triggered: Vec<InputSemantic> = _input_manager.get_semantic_presses().to_vec();
+        triggered.extend(BOARD_ACTIONS.iter().map(|(semantic, _)| *semantic).filter(|s| _input_manager.is_repeated(*s)));
+        for semantic in triggered {
+            if let Some((_, action)) = BOARD_ACTIONS.iter().find(|(s, _)| *s == semantic) {
+                if *action == BoardAction::Hint && !self.hints_enabled { continue; }
+                self.queue_board_action(action.clone());
+            }
         }
-        if _input_manager.get_input_state(InputSemantic::Back) == InputState::Pressed {
-            self.undo_last_action();
+    }
+    fn wants_text_input(&self) -> bool {
+        self.expression_entry.is_some() && !self.round_over && self.practice_result.is_none()
+    }
+    fn process_text_input(&mut self, _channel: &mut TextInputChannel) {
+        if let Some(entry) = &mut self.expression_entry {
+            entry.process_text_input(_channel);
         }
     }
 }
@@ -360,44 +1080,61 @@ impl ControllableGameObject for GameController {
 pub struct RenderText {
     transform: Transform,
     text: Text,
-    text_color: Color
+    text_color: Color,
+    layout: TextLayout
 }
 impl RenderText {
     pub fn new(x: f32, y: f32, depth: i32, text: &str, color: Color) -> RenderText {
-        RenderText { transform: Transform{x, y, depth}, text: Text::new(text), text_color: color }
+        RenderText { transform: Transform::new(x, y, depth), text: Text::new(text), text_color: color, layout: TextLayout::top_left() }
+    }
+    pub fn get_transform(&self) -> Transform {
+        self.transform
+    }
+    /// Centers the text on its position instead of hanging it from its top-left corner.
+    pub fn set_centered(&mut self) {
+        self.layout = TextLayout::center();
+        self.text.set_layout(self.layout);
     }
     pub fn set_text(&mut self, new_text: String) {
         self.text = Text::new(&new_text);
+        self.text.set_layout(self.layout);
     }
     pub fn set_text_color(&mut self, text_color: Color) {
         self.text_color = text_color;
     }
-    pub fn set_pos(&mut self, new_x: f32, new_y: f32) {
-        self.transform.x = new_x;
-        self.transform.y = new_y;
-    }
-    pub fn draw(&mut self, _canvas: &mut Canvas) {
+    pub fn draw(&mut self, _canvas: &mut Canvas, _parent: &Transform) {
+        let world = self.transform.compose(_parent);
         self.text.draw(
             _canvas, 
-            graphics::DrawParam::from([self.transform.x, self.transform.y]).color(self.text_color)
+            graphics::DrawParam::from([world.x, world.y]).scale([world.scale, world.scale]).color(self.text_color)
         );
     }
 }
 
+impl GameObject for RenderText {
+    fn draw(&mut self, _canvas: &mut Canvas, _parent: &Transform) {
+        RenderText::draw(self, _canvas, _parent);
+    }
+    fn get_transform(&self) -> Transform {
+        self.transform
+    }
+}
+
 pub struct VisibleOperation {
     value: OpType,
-    render_text: RenderText
+    tile: Tile
 }
 impl VisibleOperation {
     pub fn new(x: f32, y: f32, depth: i32, color: Color) -> VisibleOperation {
         VisibleOperation {
             value: OpType::None,
-            render_text: RenderText::new(x, y, depth, "", color)
+            tile: Tile::new(x, y, depth, color)
         }
     }
     pub fn set_operation(&mut self, new_operation_type: OpType) {
         self.value = new_operation_type;
-        self.render_text.set_text(self.get_string_representation_for_operation());
+        self.tile.set_text(self.get_string_representation_for_operation());
+        self.tile.set_state(if self.value == OpType::None { TileState::Empty } else { TileState::Filled });
     }
     fn get_string_representation_for_operation(&self) -> String {
         match self.value {
@@ -410,79 +1147,344 @@ impl VisibleOperation {
     }
 }
 impl GameObject for VisibleOperation {
-    fn get_depth(&self) -> i32 { self.render_text.transform.depth }
-    fn draw(&mut self, _canvas: &mut Canvas) {
-        self.render_text.draw(_canvas);
+    fn get_depth(&self) -> i32 { self.tile.get_depth() }
+    fn update(&mut self, _delta: f32) {
+        self.tile.update(_delta);
+    }
+    fn draw(&mut self, _canvas: &mut Canvas, _parent: &Transform) {
+        self.tile.draw(_canvas, _parent);
     }
 }
 
 pub struct VisibleNumber {
     value: Option<Fraction>,
-    render_text: RenderText
+    expression: Option<String>, //How the value was reached, e.g. "(9 + 1)"; just the value for hotbar numbers
+    selected: bool,
+    empty_state: TileState, //How the tile looks without a value
+    tile: Tile
 }
 impl VisibleNumber {
     pub fn new(value: Option<Fraction>, x: f32, y: f32, depth: i32, text_color: Color) -> VisibleNumber {
-        let text;
-        match value {
-            Some(val) => { text = val.to_string() },
-            None => { text = "".to_string() }
-        }
-        VisibleNumber {
-            value,
-            render_text: RenderText::new(x, y, depth, &text, text_color)
-        }
+        let mut number = VisibleNumber {
+            value: None,
+            expression: None,
+            selected: false,
+            empty_state: TileState::Empty,
+            tile: Tile::new(x, y, depth, text_color)
+        };
+        number.update_value(value);
+        number
+    }
+    /// For slots whose value gets used up rather than filled in, like the hotbar.
+    pub fn with_empty_state(mut self, empty_state: TileState) -> VisibleNumber {
+        self.empty_state = empty_state;
+        self.refresh_state();
+        self
     }
     pub fn update_value(&mut self, new_value: Option<Fraction>) {
+        self.update_value_with_expression(new_value, new_value.map(|val| val.to_string()));
+    }
+    pub fn update_value_with_expression(&mut self, new_value: Option<Fraction>, expression: Option<String>) {
         self.value = new_value;
+        self.expression = expression;
         match self.value {
-            Some(val) => {self.render_text.set_text(val.to_string());},
-            None => { self.render_text.set_text("".to_string()) }
+            Some(val) => {self.tile.set_text(val.to_string());},
+            None => { self.tile.set_text("".to_string()) }
+        }
+        self.refresh_state();
+    }
+    pub fn get_expression(&self) -> String {
+        match &self.expression {
+            Some(expr) => expr.clone(),
+            None => "".to_string()
         }
     }
+    pub fn set_selected(&mut self, selected: bool) {
+        self.selected = selected;
+        self.refresh_state();
+    }
+    pub fn get_position(&self) -> Point2<f32> {
+        self.tile.get_position()
+    }
+    pub fn play_animation(&mut self, animation: Animation) {
+        self.tile.play_animation(animation);
+    }
+    pub fn flash_error(&mut self) {
+        self.tile.flash_error();
+    }
+    fn refresh_state(&mut self) {
+        self.tile.set_state(match (self.value, self.selected) {
+            (_, true) => TileState::Selected,
+            (Some(_), false) => TileState::Filled,
+            (None, false) => self.empty_state
+        });
+    }
 }
 impl GameObject for VisibleNumber {
-    fn get_depth(&self) -> i32 { self.render_text.transform.depth }
-    fn draw(&mut self, _canvas: &mut Canvas) {
-        self.render_text.draw(_canvas);
+    fn get_depth(&self) -> i32 { self.tile.get_depth() }
+    fn update(&mut self, _delta: f32) {
+        self.tile.update(_delta);
+    }
+    fn draw(&mut self, _canvas: &mut Canvas, _parent: &Transform) {
+        self.tile.draw(_canvas, _parent);
+    }
+}
+
+pub struct VisibleExpression {
+    field: TextField,
+    entry_text: RenderText,
+    error_text: RenderText
+}
+impl VisibleExpression {
+    pub fn new(x: f32, y: f32, depth: i32, color: Color) -> VisibleExpression {
+        let mut expression = VisibleExpression {
+            field: TextField::new().with_filter(|c| c.is_ascii_digit() || "+-*/xX() ".contains(c)),
+            entry_text: RenderText::new(x, y, depth, "", color),
+            error_text: RenderText::new(x, y+20.0, depth, "", Color::RED)
+        };
+        expression.refresh();
+        expression
+    }
+    pub fn get_text(&self) -> String {
+        self.field.get_text()
+    }
+    pub fn process_text_input(&mut self, channel: &mut TextInputChannel) {
+        if self.field.process_text_input(channel) {
+            self.set_error(None);
+        }
+        else {
+            self.refresh(); //The cursor may still have moved
+        }
+    }
+    pub fn clear(&mut self) {
+        self.field.clear();
+        self.set_error(None);
+    }
+    pub fn set_error(&mut self, error: Option<String>) {
+        match error {
+            Some(msg) => { self.error_text.set_text(msg); },
+            None => { self.error_text.set_text("".to_string()); }
+        }
+        self.refresh();
+    }
+    fn refresh(&mut self) {
+        self.entry_text.set_text(format!("> {}", self.field.get_text_with_caret('_')));
+    }
+}
+impl GameObject for VisibleExpression {
+    fn get_depth(&self) -> i32 { self.entry_text.transform.depth }
+    fn draw(&mut self, _canvas: &mut Canvas, _parent: &Transform) {
+        self.entry_text.draw(_canvas, _parent);
+        self.error_text.draw(_canvas, _parent);
+    }
+}
+
+pub struct ScoreDisplay {
+    score_text: RenderText,
+    streak_text: RenderText,
+    solved_text: RenderText,
+    award_text: RenderText
+}
+impl ScoreDisplay {
+    pub fn new(x: f32, y: f32, depth: i32) -> ScoreDisplay {
+        let rows = ListLayout::vertical(x, y, 20.0).get_points(4);
+        let row = |i: usize| rows.get(i).expect("Score display layout returned too few rows");
+        let mut display = ScoreDisplay {
+            score_text: RenderText::new(row(0).x, row(0).y, depth, "", Color::WHITE),
+            streak_text: RenderText::new(row(1).x, row(1).y, depth, "", Color::WHITE),
+            solved_text: RenderText::new(row(2).x, row(2).y, depth, "", Color::WHITE),
+            award_text: RenderText::new(row(3).x, row(3).y, depth, "", Color::GREEN)
+        };
+        display.refresh(&ScoreKeeper::new());
+        display
+    }
+    pub fn refresh(&mut self, score: &ScoreKeeper) {
+        self.score_text.set_text(format!("Score: {}", score.get_score()));
+        self.streak_text.set_text(format!("Streak: {} (best {}) x{:.1}", score.get_streak(), score.get_best_streak(), score.get_multiplier()));
+        self.solved_text.set_text(format!("Solved: {}", score.get_boards_solved()));
+        match score.get_last_award() {
+            0 => { self.award_text.set_text("".to_string()); },
+            award => { self.award_text.set_text(format!("+{}", award)); }
+        }
+    }
+}
+impl GameObject for ScoreDisplay {
+    fn get_depth(&self) -> i32 { self.score_text.transform.depth }
+    fn draw(&mut self, _canvas: &mut Canvas, _parent: &Transform) {
+        self.score_text.draw(_canvas, _parent);
+        self.streak_text.draw(_canvas, _parent);
+        self.solved_text.draw(_canvas, _parent);
+        self.award_text.draw(_canvas, _parent);
+    }
+}
+
+pub struct RoundSummary {
+    lines: Vec<RenderText>
+}
+impl RoundSummary {
+    pub fn new(x: f32, y: f32, depth: i32, mode: &GameMode, score: &ScoreKeeper) -> RoundSummary {
+        let contents = [
+            format!("{} round over!", mode.get_name()),
+            format!("Final score: {}", score.get_score()),
+            format!("Boards solved: {}", score.get_boards_solved()),
+            format!("Boards forfeited: {}", score.get_boards_forfeited()),
+            format!("Best streak: {}", score.get_best_streak()),
+            "Press Enter to play again".to_string()
+        ];
+        let mut lines = Vec::new();
+        for (line, pos) in contents.iter().zip(ListLayout::vertical(x, y, TEXT_LINE_HEIGHT).get_points(contents.len())) {
+            lines.push(RenderText::new(pos.x, pos.y, depth, line, Color::WHITE));
+        }
+        RoundSummary { lines }
+    }
+}
+impl GameObject for RoundSummary {
+    fn draw(&mut self, _canvas: &mut Canvas, _parent: &Transform) {
+        for line in self.lines.as_mut_slice() {
+            line.draw(_canvas, _parent);
+        }
+    }
+}
+
+const PRACTICE_ALTERNATIVES_SHOWN: usize = 3;
+
+pub struct PracticeResult {
+    lines: Vec<RenderText>
+}
+impl PracticeResult {
+    pub fn new(x: f32, y: f32, depth: i32, expression: &str, solutions: &[solver::Solution]) -> PracticeResult {
+        let mut contents = vec!(
+            "Solved!".to_string(),
+            format!("Your solution: {}", expression),
+            format!("Distinct solutions: {}", solutions.len())
+        );
+        let canonical = solver::get_canonical_expression(expression);
+        let alternatives: Vec<&solver::Solution> = solutions.iter()
+            .filter(|sol| Some(&sol.canonical) != canonical.as_ref()) //However the player wrote theirs
+            .take(PRACTICE_ALTERNATIVES_SHOWN)
+            .collect();
+        if !alternatives.is_empty() {
+            contents.push("Other solutions:".to_string());
+            for sol in alternatives {
+                contents.push(format!("    {}", sol.expression));
+            }
+        }
+        contents.push("Press Enter to continue".to_string());
+        let mut lines = Vec::new();
+        for (line, pos) in contents.iter().zip(ListLayout::vertical(x, y, TEXT_LINE_HEIGHT).get_points(contents.len())) {
+            lines.push(RenderText::new(pos.x, pos.y, depth, line, Color::WHITE));
+        }
+        PracticeResult { lines }
+    }
+}
+impl GameObject for PracticeResult {
+    fn draw(&mut self, _canvas: &mut Canvas, _parent: &Transform) {
+        for line in self.lines.as_mut_slice() {
+            line.draw(_canvas, _parent);
+        }
     }
 }
 
+const BOARD_SLOT_WIDTH: f32 = 40.0;
+const BOARD_ROW_HEIGHT: f32 = 40.0;
+const HOTBAR_COLUMNS: usize = 8; //Bigger hands wrap onto another row below
+const WORKBENCH_SLOTS: usize = 3;
+const TEXT_LINE_HEIGHT: f32 = 24.0;
+
+/// Where the center of each tile goes, relative to the board's center:
+/// the target above, the workbench on the center line, the hotbar one row below it and the operator palette under the hotbar.
 pub struct BoardLayout {
     hotbar_pos_vec: Vec<Point2<f32>>,
     workbench_pos_vec: Vec<Point2<f32>>,
+    palette_pos_vec: Vec<Point2<f32>>,
     target_pos: Point2<f32>
 }
 impl BoardLayout {
     pub fn new(center_x: f32, center_y: f32, target_offset: f32, items: usize) -> BoardLayout {
-        let hll = HorizontalListLayout {
-            transform: Transform {x: center_x, y: center_y+40.0, depth: 0},
-            x_spacing: 40.0
-        };
-        let hll_2 = HorizontalListLayout {
-            transform: Transform {x: center_x, y: center_y, depth: 0},
-            x_spacing: 40.0
-        };
+        let hotbar = GridLayout::new(center_x, center_y+BOARD_ROW_HEIGHT, HOTBAR_COLUMNS, BOARD_SLOT_WIDTH, BOARD_ROW_HEIGHT);
+        let workbench = ListLayout::horizontal(center_x, center_y, BOARD_SLOT_WIDTH);
+        let hotbar_rows = items.div_ceil(HOTBAR_COLUMNS).max(1);
+        let palette = ListLayout::horizontal(center_x, center_y+BOARD_ROW_HEIGHT*(1+hotbar_rows) as f32, BOARD_SLOT_WIDTH);
+        //Layouts give the top-left of each cell; tiles are placed by their center
+        let to_center = |p: Point2<f32>| Point2 { x: p.x+BOARD_SLOT_WIDTH/2.0, y: p.y+BOARD_ROW_HEIGHT/2.0 };
         BoardLayout {
-            hotbar_pos_vec: hll.get_points(items),
-            workbench_pos_vec: hll_2.get_points(3),
+            hotbar_pos_vec: hotbar.get_points(items).into_iter().map(to_center).collect(),
+            workbench_pos_vec: workbench.get_points(WORKBENCH_SLOTS).into_iter().map(to_center).collect(),
+            palette_pos_vec: palette.get_points(PALETTE_OPERATIONS.len()).into_iter().map(to_center).collect(),
             target_pos: Point2 { x: center_x, y: center_y-target_offset}
         }
     }
 }
-pub struct HorizontalListLayout {
-    transform: Transform,
-    x_spacing: f32
-}
-impl HorizontalListLayout {
-    pub fn get_points(&self, input_size: usize) -> Vec<Point2<f32>> {
-        let mut vec = Vec::new();
-        let width = (input_size as f32)*self.x_spacing;
-        let left = self.transform.x-width/2.0;
-        let mut i = 0.0;
-        while i < width {
-            vec.push(Point2{ x: left+i, y: self.transform.y });
-            i+=self.x_spacing;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //Three boards that all make 10 by adding everything up
+    fn start_controller(mode: GameMode) -> GameController {
+        let pools: DifficultyPools = serde_json::from_str(r#"{"easy": [[1, 2, 3, 4], [1, 1, 4, 4], [2, 2, 3, 3]], "moderate": [], "hard": []}"#).expect("");
+        let mut pool_map = HashMap::new();
+        pool_map.insert("10".to_string(), pools);
+        let config = SetConfig::new(3, Some(Fraction::from(10)), None, vec!(InputDifficulty::Easy));
+        let mut controller = GameController::new(BoardContainer::with_seed(pool_map, config, 1), mode);
+        controller.start_round().expect("");
+        controller
+    }
+    fn get_input(controller: &GameController) -> Vec<i32> {
+        controller.current_board.as_ref().expect("").input.clone()
+    }
+
+    #[test]
+    fn restarting_during_the_victory_pulse_keeps_the_board() {
+        let mut controller = start_controller(GameMode::Endless);
+        let input = get_input(&controller);
+        controller.on_board_solved("1 + 2 + 3 + 4".to_string());
+        controller.queued_actions.push_back(BoardAction::MoveToWorkbench(0));
+        controller.drag = Some(PointerDrag { source: BoardSlot::Hotbar(1), start: Point2 { x: 0.0, y: 0.0 }, dragging: true });
+        controller.restart_board();
+        assert!(controller.pending_solve.is_none());
+        assert!(controller.queued_actions.is_empty());
+        assert!(controller.drag.is_none());
+        for _ in 0..60 {
+            controller.update(1.0/60.0);
         }
-        return vec;
+        assert_eq!(get_input(&controller), input);
+    }
+
+    #[test]
+    fn solved_boards_advance_once_the_pulse_is_over() {
+        let mut controller = start_controller(GameMode::Endless);
+        let input = get_input(&controller);
+        controller.on_board_solved("1 + 2 + 3 + 4".to_string());
+        controller.update(PULSE_ANIMATION_SECONDS/2.0);
+        assert_eq!(get_input(&controller), input);
+        controller.update(PULSE_ANIMATION_SECONDS);
+        assert_ne!(get_input(&controller), input);
+    }
+
+    #[test]
+    fn dragged_tiles_draw_over_the_rest_of_the_board() {
+        let mut controller = start_controller(GameMode::Endless);
+        let vb = controller.visible_board.as_mut().expect("");
+        let first = vb.get_handle(BoardSlot::Hotbar(0)).expect("");
+        assert_eq!(vb.pieces.get_children(None).first(), Some(&first));
+        vb.set_drag_position(BoardSlot::Hotbar(0), Some(Point2 { x: 0.0, y: 0.0 }));
+        assert_eq!(vb.pieces.get_children(None).last(), Some(&first));
+        vb.set_drag_position(BoardSlot::Hotbar(0), None);
+        assert_eq!(vb.pieces.get_children(None).first(), Some(&first));
+    }
+
+    #[test]
+    fn tiles_move_with_the_board() {
+        let mut controller = start_controller(GameMode::Endless);
+        controller.set_viewport(&Viewport::reference());
+        let vb = controller.visible_board.as_mut().expect("");
+        let target = vb.get_tile(BoardSlot::Target).expect("").get_position();
+        let center = controller.transform.compose(&Transform::origin());
+        let (x, y) = (center.get_position().x+target.x, center.get_position().y+target.y);
+        assert_eq!(controller.get_slot_at(x, y), Some(BoardSlot::Target));
+        controller.visible_board.as_mut().expect("").transform = Transform::new(500.0, 0.0, 0);
+        assert_eq!(controller.get_slot_at(x, y), None);
+        assert_eq!(controller.get_slot_at(x+500.0, y), Some(BoardSlot::Target));
     }
-}
\ No newline at end of file
+}
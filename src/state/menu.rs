@@ -0,0 +1,710 @@
+use crate::util::{
+    input_manager::{self, InputContextKind, InputSemantic, InputType, InputManager},
+    bindings::{self, Bindings, REBINDABLE_SEMANTICS},
+    data_generator::{self, DifficultyPools, InputDifficulty, SetConfig, TargetValidatorFunc},
+    viewport::{Viewport, REFERENCE_WIDTH, REFERENCE_HEIGHT},
+    layout::{LayoutBox, ListLayout, Padding},
+    animation::AnimationInputPolicy,
+    timer::Countdown
+};
+use crate::state::{
+    game_object::{GameObject, RenderText, Transform, BoardContainer, GameController},
+    game_mode::{GameMode, GameSettings},
+    scene::{Scene, SceneTransition, SceneMessage, GameplayScene}
+};
+use fraction::Fraction;
+use ggez::{graphics::{Canvas, Color, DrawParam, Quad, Rect}, mint::Point2};
+use log::warn;
+use std::collections::HashMap;
+
+const MENU_LINE_HEIGHT: f32 = 28.0;
+const MENU_PANEL_WIDTH: f32 = 264.0;
+const MENU_PANEL_PADDING: f32 = 12.0;
+const MENU_TITLE_HEIGHT: f32 = 40.0;
+
+/* #region Menu widget */
+pub struct MenuItem {
+    label: String,
+    options: Vec<String>, //Empty for plain actions
+    selected: usize
+}
+impl MenuItem {
+    pub fn action(label: &str) -> MenuItem {
+        MenuItem { label: label.to_string(), options: Vec::new(), selected: 0 }
+    }
+    pub fn choice(label: &str, options: Vec<&str>, selected: usize) -> MenuItem {
+        MenuItem { label: label.to_string(), options: options.iter().map(|o| o.to_string()).collect(), selected }
+    }
+    fn get_display_text(&self) -> String {
+        match self.options.get(self.selected) {
+            Some(option) => format!("{}: < {} >", self.label, option),
+            None => self.label.clone()
+        }
+    }
+}
+
+pub enum MenuEvent {
+    None,
+    Activated(usize), //Accept on an item
+    Changed, //The item under the cursor had its option cycled
+    Back
+}
+
+pub struct Menu {
+    items: Vec<MenuItem>,
+    cursor: usize,
+    lines: Vec<RenderText>,
+    width: f32, //Clickable size of each line
+    line_height: f32,
+    last_pointer: Point2<f32> //Hover only moves the cursor when the pointer itself moves
+}
+impl Menu {
+    pub fn new(x: f32, y: f32, depth: i32, width: f32, line_height: f32, items: Vec<MenuItem>) -> Menu {
+        let mut lines = Vec::new();
+        for pos in ListLayout::vertical(x, y, line_height).get_points(items.len()) {
+            lines.push(RenderText::new(pos.x, pos.y, depth, "", Color::WHITE));
+        }
+        let mut menu = Menu { items, cursor: 0, lines, width, line_height, last_pointer: Point2 { x: 0.0, y: 0.0 } };
+        menu.refresh();
+        menu
+    }
+    pub fn set_label(&mut self, item: usize, label: &str) {
+        if let Some(i) = self.items.get_mut(item) {
+            i.label = label.to_string();
+        }
+        self.refresh();
+    }
+    pub fn get_selected_option(&self, item: usize) -> usize {
+        match self.items.get(item) {
+            Some(i) => i.selected,
+            None => 0
+        }
+    }
+    /// `origin` is the transform the menu is drawn with, so the pointer can be hit-tested against its lines.
+    pub fn process_input(&mut self, input_manager: &InputManager, origin: &Transform) -> MenuEvent {
+        let mut event = MenuEvent::None;
+        if self.items.is_empty() { return event; }
+        let pointer = input_manager.get_cursor_position();
+        let hovered = self.get_item_at(pointer, origin);
+        if pointer != self.last_pointer {
+            if let Some(i) = hovered { self.cursor = i; }
+            self.last_pointer = pointer;
+        }
+        if input_manager.is_pressed_or_repeated(InputSemantic::Up) {
+            self.cursor = (self.cursor + self.items.len() - 1)%self.items.len();
+        }
+        if input_manager.is_pressed_or_repeated(InputSemantic::Down) {
+            self.cursor = (self.cursor + 1)%self.items.len();
+        }
+        let item = self.items.get_mut(self.cursor).expect("");
+        let option_count = item.options.len();
+        if option_count > 0 {
+            if input_manager.is_pressed_or_repeated(InputSemantic::Left) {
+                item.selected = (item.selected + option_count - 1)%option_count;
+                event = MenuEvent::Changed;
+            }
+            if input_manager.is_pressed_or_repeated(InputSemantic::Right) {
+                item.selected = (item.selected + 1)%option_count;
+                event = MenuEvent::Changed;
+            }
+        }
+        if input_manager.was_pressed_this_frame(InputSemantic::Accept) {
+            event = MenuEvent::Activated(self.cursor);
+        }
+        if input_manager.was_pressed_this_frame(InputSemantic::Pointer) {
+            if let Some(i) = hovered {
+                //Clicking a choice cycles it, clicking an action activates it
+                self.cursor = i;
+                let item = self.items.get_mut(i).expect("");
+                if item.options.is_empty() {
+                    event = MenuEvent::Activated(i);
+                }
+                else {
+                    item.selected = (item.selected + 1)%item.options.len();
+                    event = MenuEvent::Changed;
+                }
+            }
+        }
+        if input_manager.was_pressed_this_frame(InputSemantic::Back) {
+            event = MenuEvent::Back;
+        }
+        self.refresh();
+        event
+    }
+    fn get_item_at(&self, point: Point2<f32>, origin: &Transform) -> Option<usize> {
+        for (i, line) in self.lines.iter().enumerate() {
+            let world = line.get_transform().compose(origin);
+            let top_left = world.get_position();
+            let scale = world.get_scale();
+            if point.x >= top_left.x && point.x < top_left.x+self.width*scale &&
+                point.y >= top_left.y && point.y < top_left.y+self.line_height*scale
+            {
+                return Some(i);
+            }
+        }
+        None
+    }
+    fn refresh(&mut self) {
+        for (i, (item, line)) in self.items.iter().zip(self.lines.iter_mut()).enumerate() {
+            if i == self.cursor {
+                line.set_text(format!("> {}", item.get_display_text()));
+                line.set_text_color(Color::YELLOW);
+            }
+            else {
+                line.set_text(format!("  {}", item.get_display_text()));
+                line.set_text_color(Color::WHITE);
+            }
+        }
+    }
+}
+impl GameObject for Menu {
+    fn draw(&mut self, _canvas: &mut Canvas, _parent: &Transform) {
+        for line in self.lines.as_mut_slice() {
+            line.draw(_canvas, _parent);
+        }
+    }
+}
+/* #endregion */
+
+/* #region Shared options */
+const DIFFICULTY_OPTIONS: &'static [&'static str] = &["All", "Easy", "Moderate", "Hard", "Easy + Moderate", "Moderate + Hard"];
+const TOGGLE_OPTIONS: &'static [&'static str] = &["On", "Off"];
+const ANIMATION_INPUT_OPTIONS: &'static [&'static str] = &["Queue", "Ignore"];
+
+fn get_difficulty_option(index: usize) -> Vec<InputDifficulty> {
+    match index {
+        1 => vec!(InputDifficulty::Easy),
+        2 => vec!(InputDifficulty::Moderate),
+        3 => vec!(InputDifficulty::Hard),
+        4 => vec!(InputDifficulty::Easy, InputDifficulty::Moderate),
+        5 => vec!(InputDifficulty::Moderate, InputDifficulty::Hard),
+        _ => vec!(InputDifficulty::Easy, InputDifficulty::Moderate, InputDifficulty::Hard)
+    }
+}
+fn find_difficulty_option(difficulties: &[InputDifficulty]) -> usize {
+    for i in 0..DIFFICULTY_OPTIONS.len() {
+        let option = get_difficulty_option(i);
+        if option.len() == difficulties.len() && option.iter().all(|d| difficulties.contains(d)) {
+            return i;
+        }
+    }
+    0
+}
+/// Builds a menu screen's title and item list inside a panel centered horizontally at the reference resolution,
+/// with its vertical position given as a fraction of the screen height.
+fn build_menu_panel(title: &str, title_color: Color, y_percent: f32, items: Vec<MenuItem>) -> (RenderText, Menu) {
+    build_sized_menu_panel(title, title_color, y_percent, MENU_PANEL_WIDTH, MENU_LINE_HEIGHT, items)
+}
+/// Same as `build_menu_panel`, for screens whose items need a wider panel or tighter lines.
+fn build_sized_menu_panel(title: &str, title_color: Color, y_percent: f32, width: f32, line_height: f32, items: Vec<MenuItem>) -> (RenderText, Menu) {
+    let screen = LayoutBox::new(0.0, 0.0, REFERENCE_WIDTH, REFERENCE_HEIGHT);
+    let height = MENU_TITLE_HEIGHT+(items.len() as f32)*line_height+2.0*MENU_PANEL_PADDING;
+    let panel = screen.anchored_in(0.5, y_percent, width, height).inset(&Padding::uniform(MENU_PANEL_PADDING));
+    let top_left = panel.get_top_left();
+    (
+        RenderText::new(top_left.x, top_left.y, 0, title, title_color),
+        Menu::new(top_left.x, top_left.y+MENU_TITLE_HEIGHT, 0, panel.width, line_height, items)
+    )
+}
+fn draw_dimmer(canvas: &mut Canvas) {
+    let screen = canvas.screen_coordinates().unwrap_or(Rect::new(0.0, 0.0, REFERENCE_WIDTH, REFERENCE_HEIGHT));
+    canvas.draw(&Quad, DrawParam::new().dest_rect(screen).color(Color::from([0.0, 0.0, 0.0, 0.6])));
+}
+/* #endregion */
+
+/* #region Main menu */
+const MODE_ITEM: usize = 0;
+const DIFFICULTY_ITEM: usize = 1;
+const SIZE_ITEM: usize = 2;
+const TARGET_ITEM: usize = 3;
+const VALIDATOR_ITEM: usize = 4;
+const START_ITEM: usize = 5;
+const CONTROLS_ITEM: usize = 6;
+const QUIT_ITEM: usize = 7;
+
+const MODE_NAMES: &'static [&'static str] = &["endless", "blitz", "timelimit", "survival", "practice"];
+const SIZE_OPTIONS: &'static [usize] = &[5, 10, 20, 50];
+const TARGET_OPTIONS: &'static [Option<i32>] = &[None, Some(10), Some(12), Some(24), Some(36)];
+const VALIDATOR_OPTIONS: &'static [&'static str] = &["Positive integers", "Integers", "Anything"];
+
+pub struct MainMenuScene {
+    pool_map: HashMap<String, DifficultyPools>,
+    root: Transform, //Menus are laid out at the reference resolution and fitted to the window
+    title: RenderText,
+    menu: Menu,
+    message: RenderText, //Why the last start failed, if it did
+    seed: Option<u64> //Every game started from here deals the same boards, for recordings
+}
+impl MainMenuScene {
+    pub fn new(pool_map: HashMap<String, DifficultyPools>, default_mode: GameMode) -> MainMenuScene {
+        let mode_names: Vec<String> = MODE_NAMES.iter().map(|n| GameMode::from_name(n).expect("").get_name()).collect();
+        let default_mode_index = MODE_NAMES.iter().position(|n| GameMode::from_name(n) == Some(default_mode)).unwrap_or(0);
+        let size_names: Vec<String> = SIZE_OPTIONS.iter().map(|s| s.to_string()).collect();
+        let target_names: Vec<String> = TARGET_OPTIONS.iter().map(|t| match t {
+            Some(val) => val.to_string(),
+            None => "Random".to_string()
+        }).collect();
+        let items = vec!(
+            MenuItem::choice("Mode", mode_names.iter().map(|s| s.as_str()).collect(), default_mode_index),
+            MenuItem::choice("Difficulty", DIFFICULTY_OPTIONS.to_vec(), 0),
+            MenuItem::choice("Boards per set", size_names.iter().map(|s| s.as_str()).collect(), 1),
+            MenuItem::choice("Target", target_names.iter().map(|s| s.as_str()).collect(), 0),
+            MenuItem::choice("Random targets", VALIDATOR_OPTIONS.to_vec(), 0),
+            MenuItem::action("Start"),
+            MenuItem::action("Controls"),
+            MenuItem::action("Quit")
+        );
+        let item_count = items.len();
+        let (title, menu) = build_menu_panel("Any4", Color::RED, 0.4, items);
+        let below_menu = title.get_transform().get_position();
+        let message = RenderText::new(below_menu.x, below_menu.y+MENU_TITLE_HEIGHT+(item_count as f32)*MENU_LINE_HEIGHT+MENU_PANEL_PADDING, 0, "", Color::RED);
+        MainMenuScene {
+            pool_map,
+            root: Transform::fitted(&Viewport::reference()),
+            title,
+            menu,
+            message,
+            seed: None
+        }
+    }
+    pub fn with_seed(mut self, seed: Option<u64>) -> MainMenuScene {
+        self.seed = seed;
+        self
+    }
+    fn build_config(&self) -> SetConfig {
+        let difficulties = get_difficulty_option(self.menu.get_selected_option(DIFFICULTY_ITEM));
+        let validator: Option<TargetValidatorFunc> = match self.menu.get_selected_option(VALIDATOR_ITEM) {
+            0 => Some(data_generator::value_is_positive_integer),
+            1 => Some(data_generator::value_is_integer),
+            _ => None
+        };
+        let size = *SIZE_OPTIONS.get(self.menu.get_selected_option(SIZE_ITEM)).expect("");
+        let target = TARGET_OPTIONS.get(self.menu.get_selected_option(TARGET_ITEM)).expect("").map(Fraction::from);
+        SetConfig::new(size, target, validator, difficulties)
+    }
+    fn build_mode(&self) -> GameMode {
+        let name = MODE_NAMES.get(self.menu.get_selected_option(MODE_ITEM)).expect("");
+        GameMode::from_name(name).expect("")
+    }
+}
+impl Scene for MainMenuScene {
+    fn process_input(&mut self, input_manager: &InputManager) -> SceneTransition {
+        match self.menu.process_input(input_manager, &self.root) {
+            MenuEvent::Activated(START_ITEM) => {
+                let board = match self.seed {
+                    Some(seed) => BoardContainer::with_seed(self.pool_map.clone(), self.build_config(), seed),
+                    None => BoardContainer::new(self.pool_map.clone(), self.build_config())
+                };
+                let mut controller = GameController::new(board, self.build_mode());
+                match controller.start_round() {
+                    Ok(()) => {
+                        self.message.set_text("".to_string());
+                        SceneTransition::Push(Box::new(GameplayScene::new(controller)))
+                    },
+                    Err(e) => {
+                        warn!("Could not start a game with these settings: {}", e);
+                        self.message.set_text(format!("Can't start with these settings: {}", e));
+                        SceneTransition::None
+                    }
+                }
+            },
+            MenuEvent::Activated(CONTROLS_ITEM) => SceneTransition::Push(Box::new(ControlsScene::from_input_manager(input_manager))),
+            MenuEvent::Activated(QUIT_ITEM) => SceneTransition::Pop,
+            _ => SceneTransition::None //Including Back: there is nothing under this menu, and a stray right-click shouldn't quit
+        }
+    }
+    fn draw(&mut self, canvas: &mut Canvas) {
+        self.title.draw(canvas, &self.root);
+        self.menu.draw(canvas, &self.root);
+        self.message.draw(canvas, &self.root);
+    }
+    fn resize(&mut self, viewport: &Viewport) {
+        self.root = Transform::fitted(viewport);
+    }
+}
+/* #endregion */
+
+/* #region Pause menu */
+const RESUME_ITEM: usize = 0;
+const RESTART_ITEM: usize = 1;
+const SETTINGS_ITEM: usize = 2;
+const RETURN_TO_MENU_ITEM: usize = 3;
+
+/// Drawn over the gameplay scene. While it is on the stack the gameplay scene gets no input or updates,
+/// so every clock in the round is frozen.
+pub struct PauseScene {
+    settings: GameSettings, //What the settings screen starts from
+    root: Transform, //Menus are laid out at the reference resolution and fitted to the window
+    title: RenderText,
+    menu: Menu
+}
+impl PauseScene {
+    pub fn new(settings: GameSettings) -> PauseScene {
+        let items = vec!(
+            MenuItem::action("Resume"),
+            MenuItem::action("Restart board"),
+            MenuItem::action("Settings"),
+            MenuItem::action("Return to menu")
+        );
+        let (title, menu) = build_menu_panel("Paused", Color::WHITE, 0.5, items);
+        PauseScene {
+            settings,
+            root: Transform::fitted(&Viewport::reference()),
+            title,
+            menu
+        }
+    }
+}
+impl Scene for PauseScene {
+    fn process_input(&mut self, input_manager: &InputManager) -> SceneTransition {
+        if input_manager.was_pressed_this_frame(InputSemantic::Pause) {
+            return SceneTransition::Pop;
+        }
+        match self.menu.process_input(input_manager, &self.root) {
+            MenuEvent::Activated(RESUME_ITEM) | MenuEvent::Back => SceneTransition::Pop,
+            MenuEvent::Activated(RESTART_ITEM) => SceneTransition::PopWith(SceneMessage::RestartBoard),
+            MenuEvent::Activated(SETTINGS_ITEM) => SceneTransition::Push(Box::new(SettingsScene::new(self.settings.clone()))),
+            MenuEvent::Activated(RETURN_TO_MENU_ITEM) => SceneTransition::PopWith(SceneMessage::ReturnToMenu),
+            _ => SceneTransition::None
+        }
+    }
+    fn draw(&mut self, canvas: &mut Canvas) {
+        draw_dimmer(canvas);
+        self.title.draw(canvas, &self.root);
+        self.menu.draw(canvas, &self.root);
+    }
+    fn resize(&mut self, viewport: &Viewport) {
+        self.root = Transform::fitted(viewport);
+    }
+    fn is_overlay(&self) -> bool { true }
+    fn handle_message(&mut self, message: SceneMessage) -> SceneTransition {
+        //Applying settings passes them on to the game underneath and resumes it
+        match message {
+            SceneMessage::ApplySettings(_) => SceneTransition::PopWith(message),
+            _ => SceneTransition::None
+        }
+    }
+}
+/* #endregion */
+
+/* #region Settings */
+const SETTINGS_DIFFICULTY_ITEM: usize = 0;
+const SETTINGS_HINTS_ITEM: usize = 1;
+const SETTINGS_ANIMATION_INPUT_ITEM: usize = 2;
+const SETTINGS_CONTROLS_ITEM: usize = 3;
+const SETTINGS_APPLY_ITEM: usize = 4;
+const SETTINGS_CANCEL_ITEM: usize = 5;
+
+pub struct SettingsScene {
+    root: Transform,
+    title: RenderText,
+    menu: Menu
+}
+impl SettingsScene {
+    pub fn new(settings: GameSettings) -> SettingsScene {
+        let items = vec!(
+            MenuItem::choice("Difficulty", DIFFICULTY_OPTIONS.to_vec(), find_difficulty_option(&settings.difficulties)),
+            MenuItem::choice("Hints", TOGGLE_OPTIONS.to_vec(), if settings.hints_enabled { 0 } else { 1 }),
+            MenuItem::choice("Input while animating", ANIMATION_INPUT_OPTIONS.to_vec(), match settings.animation_input {
+                AnimationInputPolicy::Queue => 0,
+                AnimationInputPolicy::Block => 1
+            }),
+            MenuItem::action("Controls"),
+            MenuItem::action("Apply"),
+            MenuItem::action("Cancel")
+        );
+        let (title, menu) = build_menu_panel("Settings", Color::WHITE, 0.5, items);
+        SettingsScene {
+            root: Transform::fitted(&Viewport::reference()),
+            title,
+            menu
+        }
+    }
+    fn build_settings(&self) -> GameSettings {
+        GameSettings {
+            difficulties: get_difficulty_option(self.menu.get_selected_option(SETTINGS_DIFFICULTY_ITEM)),
+            hints_enabled: self.menu.get_selected_option(SETTINGS_HINTS_ITEM) == 0,
+            animation_input: match self.menu.get_selected_option(SETTINGS_ANIMATION_INPUT_ITEM) {
+                0 => AnimationInputPolicy::Queue,
+                _ => AnimationInputPolicy::Block
+            }
+        }
+    }
+}
+impl Scene for SettingsScene {
+    fn process_input(&mut self, input_manager: &InputManager) -> SceneTransition {
+        match self.menu.process_input(input_manager, &self.root) {
+            MenuEvent::Activated(SETTINGS_CONTROLS_ITEM) => SceneTransition::Push(Box::new(ControlsScene::from_input_manager(input_manager))),
+            MenuEvent::Activated(SETTINGS_APPLY_ITEM) => SceneTransition::PopWith(SceneMessage::ApplySettings(self.build_settings())),
+            MenuEvent::Activated(SETTINGS_CANCEL_ITEM) | MenuEvent::Back => SceneTransition::Pop,
+            _ => SceneTransition::None
+        }
+    }
+    fn draw(&mut self, canvas: &mut Canvas) {
+        draw_dimmer(canvas);
+        self.title.draw(canvas, &self.root);
+        self.menu.draw(canvas, &self.root);
+    }
+    fn resize(&mut self, viewport: &Viewport) {
+        self.root = Transform::fitted(viewport);
+    }
+    fn is_overlay(&self) -> bool { true }
+}
+/* #endregion */
+
+/* #region Controls */
+const CONTROLS_PANEL_WIDTH: f32 = 560.0;
+const CONTROLS_LINE_HEIGHT: f32 = 20.0; //Tighter than other menus so every semantic fits on one screen
+const CONTROLS_RESET_ITEM: usize = REBINDABLE_SEMANTICS.len(); //Items before this are one per semantic
+const CONTROLS_SAVE_ITEM: usize = REBINDABLE_SEMANTICS.len()+1;
+const CONTROLS_CANCEL_ITEM: usize = REBINDABLE_SEMANTICS.len()+2;
+const CAPTURE_TIMEOUT: f32 = 5.0; //Seconds without a bindable press before a capture gives up
+
+/// Waiting for the next key or button press to bind.
+struct BindingCapture {
+    semantic: InputSemantic,
+    conflicting: Option<InputType>, //Already used elsewhere; pressing it a second time confirms moving it
+    prompt: String, //Shown in the title, followed by the time left
+    countdown: Countdown //Cancels the capture when it runs out, so no key has to be kept back for cancelling
+}
+
+/// Lists every semantic with its inputs. Activating one captures the next key or button press (with any modifiers
+/// held) and makes it that semantic's input. Changes only take effect on save, when the game also writes them to the bindings file.
+pub struct ControlsScene {
+    bindings: Bindings, //Working copy
+    root: Transform,
+    title: RenderText,
+    menu: Menu,
+    capture: Option<BindingCapture>
+}
+impl ControlsScene {
+    pub fn new(bindings: Bindings) -> ControlsScene {
+        let mut items: Vec<MenuItem> = REBINDABLE_SEMANTICS.iter().map(|s| MenuItem::action(&get_binding_label(&bindings, *s))).collect();
+        items.push(MenuItem::action("Reset to defaults"));
+        items.push(MenuItem::action("Save"));
+        items.push(MenuItem::action("Cancel"));
+        let (title, menu) = build_sized_menu_panel("Controls", Color::WHITE, 0.5, CONTROLS_PANEL_WIDTH, CONTROLS_LINE_HEIGHT, items);
+        ControlsScene {
+            bindings,
+            root: Transform::fitted(&Viewport::reference()),
+            title,
+            menu,
+            capture: None
+        }
+    }
+    /// Starts from whatever the game is currently using.
+    pub fn from_input_manager(input_manager: &InputManager) -> ControlsScene {
+        ControlsScene::new(input_manager.get_bindings().cloned().unwrap_or_else(Bindings::defaults))
+    }
+    fn refresh(&mut self) {
+        for (i, semantic) in REBINDABLE_SEMANTICS.iter().enumerate() {
+            self.menu.set_label(i, &get_binding_label(&self.bindings, *semantic));
+        }
+        self.title.set_text("Controls".to_string());
+    }
+    fn start_capture(&mut self, semantic: InputSemantic) {
+        let prompt = format!("Press a key or button for {}", get_semantic_label(semantic));
+        self.capture = Some(BindingCapture { semantic, conflicting: None, prompt, countdown: Countdown::new(CAPTURE_TIMEOUT) });
+        self.show_capture();
+    }
+    //Restarts the countdown so the player has time to read the new prompt
+    fn set_capture_prompt(&mut self, prompt: String) {
+        if let Some(capture) = &mut self.capture {
+            capture.prompt = prompt;
+            capture.countdown.reset();
+        }
+        self.show_capture();
+    }
+    fn show_capture(&mut self) {
+        if let Some(capture) = &self.capture {
+            self.title.set_text(format!("{} (cancels in {}s)", capture.prompt, get_whole_seconds(&capture.countdown)));
+        }
+    }
+    fn process_capture(&mut self, input_manager: &InputManager) {
+        //Every press counts, so a key that lands in the same frame as a modifier or an unbindable input still gets captured
+        for input in input_manager.get_presses_this_frame() {
+            if self.capture.is_none() { return; }
+            if let InputType::Keyboard(chord) = input {
+                //Modifiers are captured as part of the key pressed while they are held
+                if input_manager::is_modifier_key(chord.key) { continue; }
+            }
+            //Only inputs the bindings file can name survive a save, and touch always drives the pointer
+            if input == InputType::Touch || bindings::get_input_from_name(&bindings::get_input_name(input)) != Some(input) {
+                self.set_capture_prompt(format!("{} can't be bound. Press another key", get_input_label(input)));
+                continue;
+            }
+            let capture = self.capture.as_mut().expect("");
+            let conflicts = self.bindings.find_conflicts(capture.semantic, input);
+            if conflicts.is_empty() || capture.conflicting == Some(input) {
+                self.bindings.rebind(capture.semantic, input);
+                self.capture = None;
+                self.refresh();
+            }
+            else {
+                capture.conflicting = Some(input);
+                let names: Vec<&str> = conflicts.iter().map(|s| get_semantic_label(*s)).collect();
+                self.set_capture_prompt(format!("{} is used by {}. Press it again to move it here", get_input_label(input), names.join(", ")));
+            }
+        }
+    }
+}
+impl Scene for ControlsScene {
+    fn process_input(&mut self, input_manager: &InputManager) -> SceneTransition {
+        if self.capture.is_some() {
+            self.process_capture(input_manager);
+            return SceneTransition::None;
+        }
+        match self.menu.process_input(input_manager, &self.root) {
+            MenuEvent::Activated(CONTROLS_RESET_ITEM) => {
+                self.bindings = Bindings::defaults();
+                self.refresh();
+                SceneTransition::None
+            },
+            MenuEvent::Activated(CONTROLS_SAVE_ITEM) => {
+                SceneTransition::PopAndRebind(self.bindings.clone())
+            },
+            MenuEvent::Activated(CONTROLS_CANCEL_ITEM) | MenuEvent::Back => SceneTransition::Pop,
+            MenuEvent::Activated(i) => {
+                //The press that opened the capture is over by next frame, so it can't bind itself
+                self.start_capture(*REBINDABLE_SEMANTICS.get(i).expect(""));
+                SceneTransition::None
+            },
+            _ => SceneTransition::None
+        }
+    }
+    fn update(&mut self, delta: f32) -> SceneTransition {
+        let (expired, shown) = match &mut self.capture {
+            Some(capture) => {
+                let shown = get_whole_seconds(&capture.countdown);
+                (capture.countdown.tick(delta), shown != get_whole_seconds(&capture.countdown))
+            },
+            None => { return SceneTransition::None; }
+        };
+        if expired {
+            self.capture = None;
+            self.refresh();
+        }
+        else if shown {
+            self.show_capture();
+        }
+        SceneTransition::None
+    }
+    fn draw(&mut self, canvas: &mut Canvas) {
+        draw_dimmer(canvas);
+        self.title.draw(canvas, &self.root);
+        self.menu.draw(canvas, &self.root);
+    }
+    fn resize(&mut self, viewport: &Viewport) {
+        self.root = Transform::fitted(viewport);
+    }
+    fn is_overlay(&self) -> bool { true }
+    fn get_input_context(&self) -> InputContextKind {
+        match self.capture {
+            Some(_) => InputContextKind::Rebinding, //Nothing the player presses should also move the menu
+            None => InputContextKind::Menu
+        }
+    }
+}
+
+fn get_whole_seconds(countdown: &Countdown) -> u32 {
+    countdown.get_remaining().ceil() as u32
+}
+fn get_semantic_label(semantic: InputSemantic) -> &'static str {
+    match semantic {
+        InputSemantic::Up => "Up",
+        InputSemantic::Down => "Down",
+        InputSemantic::Left => "Left",
+        InputSemantic::Right => "Right",
+        InputSemantic::Accept => "Accept / compute",
+        InputSemantic::Back => "Back / undo",
+        InputSemantic::Plus => "Plus",
+        InputSemantic::Minus => "Minus",
+        InputSemantic::Multiply => "Multiply",
+        InputSemantic::Divide => "Divide",
+        InputSemantic::Hotbar1 => "Hotbar 1",
+        InputSemantic::Hotbar2 => "Hotbar 2",
+        InputSemantic::Hotbar3 => "Hotbar 3",
+        InputSemantic::Hotbar4 => "Hotbar 4",
+        InputSemantic::ToggleExpressionEntry => "Expression entry",
+        InputSemantic::Hint => "Hint",
+        InputSemantic::Skip => "Skip board",
+        InputSemantic::Pause => "Pause",
+        InputSemantic::Pointer => "Click / drag"
+    }
+}
+fn get_input_label(input: InputType) -> String {
+    match input {
+        InputType::Keyboard(_) => bindings::get_input_name(input).trim_start_matches("Key:").to_string(),
+        InputType::Mouse(button) => format!("Mouse {:?}", button),
+        InputType::Gamepad(_) => bindings::get_input_name(input).replace(':', " "),
+        InputType::Touch => "Touch".to_string()
+    }
+}
+fn get_binding_label(bindings: &Bindings, semantic: InputSemantic) -> String {
+    let inputs: Vec<String> = bindings.get_inputs(semantic).iter().map(|i| get_input_label(*i)).collect();
+    if inputs.is_empty() {
+        format!("{}: (unbound)", get_semantic_label(semantic))
+    }
+    else {
+        format!("{}: {}", get_semantic_label(semantic), inputs.join(", "))
+    }
+}
+/* #endregion */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::input_manager::KeyChord;
+    use ggez::input::keyboard::{KeyCode, KeyMods};
+
+    fn key(code: KeyCode) -> InputType {
+        InputType::Keyboard(KeyChord::new(code))
+    }
+    fn capturing(semantic: InputSemantic) -> (ControlsScene, InputManager) {
+        let mut scene = ControlsScene::new(Bindings::defaults());
+        scene.start_capture(semantic);
+        let mut manager = InputManager::from_bindings(&Bindings::defaults());
+        manager.sync_contexts(&[InputContextKind::Menu, scene.get_input_context()]);
+        (scene, manager)
+    }
+    fn press_in_one_frame(scene: &mut ControlsScene, manager: &mut InputManager, inputs: &[InputType]) {
+        for input in inputs {
+            manager.process_input_pressed(*input);
+        }
+        scene.process_input(manager);
+        for input in inputs {
+            manager.process_input_released(*input);
+        }
+        manager.process_input();
+    }
+
+    #[test]
+    fn escape_can_be_bound() {
+        let (mut scene, mut manager) = capturing(InputSemantic::Hint);
+        press_in_one_frame(&mut scene, &mut manager, &[key(KeyCode::Escape)]);
+        assert!(scene.capture.is_some()); //Pause has it, so the first press only asks to confirm
+        press_in_one_frame(&mut scene, &mut manager, &[key(KeyCode::Escape)]);
+        assert!(scene.capture.is_none());
+        assert_eq!(scene.bindings.get_inputs(InputSemantic::Hint)[0], key(KeyCode::Escape));
+        assert!(!scene.bindings.get_inputs(InputSemantic::Pause).contains(&key(KeyCode::Escape)));
+    }
+
+    #[test]
+    fn keys_behind_other_presses_in_the_frame_are_captured() {
+        let (mut scene, mut manager) = capturing(InputSemantic::Hint);
+        let shift_j = InputType::Keyboard(KeyChord::new(KeyCode::J).with_mods(KeyMods::SHIFT));
+        press_in_one_frame(&mut scene, &mut manager, &[key(KeyCode::LShift), key(KeyCode::Capital), shift_j]);
+        assert!(scene.capture.is_none());
+        assert_eq!(scene.bindings.get_inputs(InputSemantic::Hint)[0], shift_j);
+    }
+
+    #[test]
+    fn captures_give_up_when_nothing_is_pressed() {
+        let (mut scene, _) = capturing(InputSemantic::Hint);
+        scene.update(CAPTURE_TIMEOUT-0.5);
+        assert_eq!(scene.capture.as_ref().map(|c| get_whole_seconds(&c.countdown)), Some(1));
+        scene.update(1.0);
+        assert!(scene.capture.is_none());
+        assert_eq!(scene.bindings, Bindings::defaults());
+    }
+}
@@ -0,0 +1,220 @@
+use crate::util::registry::{Registry, Handle};
+use crate::state::game_object::{GameObject, Transform};
+use std::collections::HashMap;
+use log::warn;
+use ggez::graphics::Canvas;
+
+pub struct RenderInstruction<T: ?Sized> {
+    handle: Handle<T>,
+    origin: Transform //World transform of the parent the object is drawn relative to
+}
+
+/// Game objects arranged into parents and children. A child's transform is relative to its parent's, so moving
+/// a parent moves everything under it. Parents draw before their children, and siblings are ordered by depth,
+/// then by insertion order. Removal is deferred like the registry's.
+pub struct ObjectTree<T: ?Sized + GameObject> {
+    objects: Registry<T>,
+    parents: HashMap<Handle<T>, Handle<T>>, //Child -> parent; objects without an entry are roots
+    children: HashMap<Handle<T>, Vec<Handle<T>>> //Parent -> children, the other way round
+}
+impl<T: ?Sized + GameObject> ObjectTree<T> {
+    pub fn new() -> ObjectTree<T> {
+        ObjectTree { objects: Registry::new(), parents: HashMap::new(), children: HashMap::new() }
+    }
+    /// Added as a root; see `set_parent`.
+    pub fn insert(&mut self, obj: Box<T>) -> Handle<T> {
+        self.objects.insert(obj)
+    }
+    pub fn get(&self, handle: Handle<T>) -> Option<&T> {
+        self.objects.get(handle)
+    }
+    pub fn get_mut(&mut self, handle: Handle<T>) -> Option<&mut T> {
+        self.objects.get_mut(handle)
+    }
+    /// The object (and everything parented to it) stays alive until the next `flush_removals`.
+    pub fn remove(&mut self, handle: Handle<T>) {
+        self.objects.remove(handle);
+        for child in self.children.get(&handle).cloned().unwrap_or_default() {
+            self.remove(child);
+        }
+    }
+    /// Makes `child` move with `parent`: its transform is then treated as relative to the parent's.
+    /// Passing None detaches it back to a root. Parenting to an object that is gone or being removed,
+    /// or that would form a cycle, is refused.
+    pub fn set_parent(&mut self, child: Handle<T>, parent: Option<Handle<T>>) {
+        if !self.is_attachable(child) {
+            warn!("Refused to reparent {:?}: it is gone or being removed", child);
+            return;
+        }
+        if let Some(p) = parent {
+            if !self.is_attachable(p) {
+                warn!("Refused to parent {:?} to {:?}: the parent is gone or being removed", child, p);
+                return;
+            }
+            let mut ancestor = Some(p);
+            while let Some(a) = ancestor {
+                if a == child {
+                    warn!("Refused to parent {:?} to {:?}: it would create a cycle", child, p);
+                    return;
+                }
+                ancestor = self.parents.get(&a).copied();
+            }
+        }
+        if let Some(old) = self.parents.remove(&child) {
+            if let Some(siblings) = self.children.get_mut(&old) {
+                siblings.retain(|c| *c != child);
+            }
+        }
+        if let Some(p) = parent {
+            self.parents.insert(child, p);
+            self.children.entry(p).or_default().push(child);
+        }
+    }
+    /// The children of `parent`, or the roots if None. Sorted by depth, then by insertion order.
+    pub fn get_children(&self, parent: Option<Handle<T>>) -> Vec<Handle<T>> {
+        let mut children = match parent {
+            Some(p) => self.children.get(&p).cloned().unwrap_or_default(),
+            None => {
+                let mut roots = self.objects.handles();
+                roots.retain(|c| !self.parents.contains_key(c));
+                roots
+            }
+        };
+        children.sort_by_key(|c| (self.get_transform(*c).map_or(0, |t| t.get_depth()), self.objects.get_sequence(*c)));
+        return children;
+    }
+    /// Every object in draw order, each with the world transform of its parent. Roots hang off `root`.
+    pub fn collect_render_instructions(&self, root: &Transform) -> Vec<RenderInstruction<T>> {
+        let mut instructions = Vec::new();
+        for handle in self.get_children(None) {
+            self.collect_subtree(handle, *root, &mut instructions);
+        }
+        return instructions;
+    }
+    pub fn process_render_instruction(&mut self, instruction: RenderInstruction<T>, canvas: &mut Canvas) {
+        //NOTE: the object only draws itself; its children get their own instructions
+        if let Some(o) = self.objects.get_mut(instruction.handle) {
+            o.draw(canvas, &instruction.origin);
+        }
+    }
+    pub fn draw(&mut self, canvas: &mut Canvas, root: &Transform) {
+        for i in self.collect_render_instructions(root) {
+            self.process_render_instruction(i, canvas);
+        }
+    }
+    pub fn update(&mut self, delta: f32) {
+        for o in self.objects.iter_mut() {
+            o.update(delta);
+        }
+    }
+    pub fn flush_removals(&mut self) {
+        self.objects.flush_removals();
+        //Children go with their parents, so a dead parent never leaves a live child behind
+        let objects = &self.objects;
+        self.parents.retain(|c, _| objects.contains(*c));
+        self.children.retain(|p, _| objects.contains(*p));
+        for siblings in self.children.values_mut() {
+            siblings.retain(|c| objects.contains(*c));
+        }
+    }
+    fn get_transform(&self, handle: Handle<T>) -> Option<Transform> {
+        self.objects.get(handle).map(|o| o.get_transform())
+    }
+    //Alive and not on its way out at the end of the frame
+    fn is_attachable(&self, handle: Handle<T>) -> bool {
+        self.objects.contains(handle) && !self.objects.is_removal_pending(handle)
+    }
+    fn collect_subtree(&self, handle: Handle<T>, origin: Transform, instructions: &mut Vec<RenderInstruction<T>>) {
+        let world = match self.get_transform(handle) {
+            Some(t) => t.compose(&origin),
+            None => { return; }
+        };
+        instructions.push(RenderInstruction { handle, origin });
+        for child in self.get_children(Some(handle)) {
+            self.collect_subtree(child, world, instructions);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::game_object::RenderText;
+    use ggez::graphics::Color;
+
+    fn add_text(tree: &mut ObjectTree<dyn GameObject>, x: f32, depth: i32) -> Handle<dyn GameObject> {
+        tree.insert(Box::new(RenderText::new(x, 0.0, depth, "", Color::WHITE)))
+    }
+    fn get_draw_order(tree: &ObjectTree<dyn GameObject>) -> Vec<Handle<dyn GameObject>> {
+        tree.collect_render_instructions(&Transform::origin()).iter().map(|i| i.handle).collect()
+    }
+
+    #[test]
+    fn children_sort_by_depth_then_insertion() {
+        let mut tree: ObjectTree<dyn GameObject> = ObjectTree::new();
+        let parent = add_text(&mut tree, 0.0, 0);
+        let deep = add_text(&mut tree, 0.0, 5);
+        let first = add_text(&mut tree, 0.0, 0);
+        let second = add_text(&mut tree, 0.0, 0);
+        for child in [second, deep, first] {
+            tree.set_parent(child, Some(parent));
+        }
+        assert_eq!(tree.get_children(Some(parent)), vec!(first, second, deep));
+        assert_eq!(tree.get_children(None), vec!(parent));
+        tree.set_parent(deep, None);
+        assert_eq!(tree.get_children(Some(parent)), vec!(first, second));
+        assert_eq!(tree.get_children(None), vec!(parent, deep));
+    }
+
+    #[test]
+    fn parents_draw_before_their_children_relative_to_them() {
+        let mut tree: ObjectTree<dyn GameObject> = ObjectTree::new();
+        let child = add_text(&mut tree, 5.0, 0);
+        let parent = add_text(&mut tree, 100.0, 0);
+        let on_top = add_text(&mut tree, 0.0, 1);
+        tree.set_parent(child, Some(parent));
+        assert_eq!(get_draw_order(&tree), vec!(parent, child, on_top));
+        let instructions = tree.collect_render_instructions(&Transform::new(10.0, 0.0, 0));
+        let child_origin = instructions.iter().find(|i| i.handle == child).expect("").origin;
+        assert_eq!(child_origin.get_position().x, 110.0); //Hangs off the parent's world position
+    }
+
+    #[test]
+    fn dead_or_dying_parents_are_refused() {
+        let mut tree: ObjectTree<dyn GameObject> = ObjectTree::new();
+        let parent = add_text(&mut tree, 0.0, 0);
+        let child = add_text(&mut tree, 0.0, 0);
+        tree.remove(parent);
+        tree.set_parent(child, Some(parent));
+        tree.flush_removals();
+        assert_eq!(tree.get_children(None), vec!(child));
+        tree.set_parent(child, Some(parent));
+        assert_eq!(tree.get_children(None), vec!(child));
+    }
+
+    #[test]
+    fn cycles_are_refused() {
+        let mut tree: ObjectTree<dyn GameObject> = ObjectTree::new();
+        let a = add_text(&mut tree, 0.0, 0);
+        let b = add_text(&mut tree, 0.0, 0);
+        tree.set_parent(b, Some(a));
+        tree.set_parent(a, Some(b));
+        assert_eq!(tree.get_children(None), vec!(a));
+        assert_eq!(tree.get_children(Some(a)), vec!(b));
+    }
+
+    #[test]
+    fn removing_a_parent_removes_its_subtree() {
+        let mut tree: ObjectTree<dyn GameObject> = ObjectTree::new();
+        let root = add_text(&mut tree, 0.0, 0);
+        let child = add_text(&mut tree, 0.0, 0);
+        let grandchild = add_text(&mut tree, 0.0, 0);
+        tree.set_parent(child, Some(root));
+        tree.set_parent(grandchild, Some(child));
+        tree.remove(root);
+        assert_eq!(get_draw_order(&tree).len(), 3); //Deferred to the end of the frame
+        tree.flush_removals();
+        assert!(get_draw_order(&tree).is_empty());
+        assert!(tree.parents.is_empty() && tree.children.is_empty());
+    }
+}
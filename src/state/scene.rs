@@ -0,0 +1,262 @@
+use crate::util::{
+    input_manager::{InputContextKind, InputSemantic, InputManager},
+    bindings::Bindings,
+    text_input::TextInputChannel,
+    viewport::Viewport
+};
+use crate::state::{
+    game_object::{GameObject, ControllableGameObject, GameController, RoundSummary, Transform},
+    game_mode::GameSettings,
+    menu::PauseScene
+};
+use ggez::graphics::Canvas;
+
+pub enum SceneTransition {
+    None,
+    Push(Box<dyn Scene>),
+    Pop,
+    PopWith(SceneMessage), //Pop, then hand the message to the scene that is now on top
+    PopAndRebind(Bindings) //Pop, and switch the game's input over to these bindings and save them
+}
+
+/// Requests passed down the stack from a popped scene to the one beneath it.
+pub enum SceneMessage {
+    RestartBoard,
+    ApplySettings(GameSettings),
+    ReturnToMenu
+}
+
+/// A full screen of the game (menu, gameplay, pause, results...). Only the top scene of the stack
+/// receives input and updates; scenes beneath an overlay keep drawing.
+pub trait Scene {
+    fn update(&mut self, _delta: f32) -> SceneTransition { SceneTransition::None }
+    fn process_input(&mut self, _input_manager: &InputManager) -> SceneTransition { SceneTransition::None }
+    /// Whether the scene should be given focus for typed text. Only the top scene of the stack can have it.
+    fn wants_text_input(&self) -> bool { false }
+    fn process_text_input(&mut self, _channel: &mut TextInputChannel) {}
+    fn draw(&mut self, _canvas: &mut Canvas) {}
+    fn is_overlay(&self) -> bool { false }
+    /// Called when the scene above this one is popped and this scene is on top again.
+    fn on_resume(&mut self) {}
+    fn handle_message(&mut self, _message: SceneMessage) -> SceneTransition { SceneTransition::None }
+    /// Called when the window changes size, and once when the scene is pushed.
+    fn resize(&mut self, _viewport: &Viewport) {}
+    /// Which mappings presses go through while this scene is in the stack. Contexts stack in scene order.
+    fn get_input_context(&self) -> InputContextKind { InputContextKind::Menu }
+}
+
+pub struct SceneStack {
+    scenes: Vec<Box<dyn Scene>>,
+    viewport: Viewport,
+    requested_bindings: Option<Bindings> //Picked up by whoever owns the input manager
+}
+impl SceneStack {
+    pub fn new() -> SceneStack {
+        SceneStack { scenes: Vec::new(), viewport: Viewport::reference(), requested_bindings: None }
+    }
+    pub fn push(&mut self, mut scene: Box<dyn Scene>) {
+        scene.resize(&self.viewport);
+        self.scenes.push(scene);
+    }
+    pub fn pop(&mut self) -> Option<Box<dyn Scene>> {
+        let popped = self.scenes.pop();
+        if let Some(top) = self.scenes.last_mut() {
+            top.on_resume();
+        }
+        popped
+    }
+    pub fn apply(&mut self, transition: SceneTransition) {
+        match transition {
+            SceneTransition::None => {},
+            SceneTransition::Push(scene) => { self.push(scene); },
+            SceneTransition::Pop => { self.pop(); },
+            SceneTransition::PopWith(message) => {
+                self.pop();
+                let next = match self.scenes.last_mut() {
+                    Some(top) => top.handle_message(message),
+                    None => SceneTransition::None
+                };
+                self.apply(next);
+            },
+            SceneTransition::PopAndRebind(bindings) => {
+                self.pop();
+                self.requested_bindings = Some(bindings);
+            }
+        }
+    }
+    pub fn take_requested_bindings(&mut self) -> Option<Bindings> {
+        self.requested_bindings.take()
+    }
+    pub fn resize(&mut self, viewport: Viewport) {
+        self.viewport = viewport;
+        for scene in self.scenes.iter_mut() {
+            scene.resize(&viewport);
+        }
+    }
+    /// What the scenes are currently laid out against.
+    pub fn get_viewport(&self) -> Viewport {
+        self.viewport
+    }
+    pub fn is_empty(&self) -> bool {
+        self.scenes.is_empty()
+    }
+    pub fn update(&mut self, delta: f32) {
+        let transition = match self.scenes.last_mut() {
+            Some(top) => top.update(delta),
+            None => SceneTransition::None
+        };
+        self.apply(transition);
+    }
+    pub fn process_input(&mut self, input_manager: &InputManager) {
+        let transition = match self.scenes.last_mut() {
+            Some(top) => top.process_input(input_manager),
+            None => SceneTransition::None
+        };
+        self.apply(transition);
+    }
+    /// One context per scene, bottom of the stack first.
+    pub fn get_input_contexts(&self) -> Vec<InputContextKind> {
+        self.scenes.iter().map(|scene| scene.get_input_context()).collect()
+    }
+    /// Only the top scene can hold text focus.
+    pub fn wants_text_input(&self) -> bool {
+        match self.scenes.last() {
+            Some(top) => top.wants_text_input(),
+            None => false
+        }
+    }
+    pub fn process_text_input(&mut self, channel: &mut TextInputChannel) {
+        if let Some(top) = self.scenes.last_mut() {
+            top.process_text_input(channel);
+        }
+    }
+    pub fn draw(&mut self, canvas: &mut Canvas) {
+        //Draw from the highest opaque scene upward so overlays sit on top of whatever they cover
+        let mut first_visible = 0;
+        for (i, scene) in self.scenes.iter().enumerate().rev() {
+            if !scene.is_overlay() {
+                first_visible = i;
+                break;
+            }
+        }
+        for scene in self.scenes[first_visible..].iter_mut() {
+            scene.draw(canvas);
+        }
+    }
+}
+
+pub struct GameplayScene {
+    controller: GameController
+}
+impl GameplayScene {
+    pub fn new(controller: GameController) -> GameplayScene {
+        GameplayScene { controller }
+    }
+}
+impl Scene for GameplayScene {
+    fn update(&mut self, delta: f32) -> SceneTransition {
+        self.controller.update(delta);
+        if self.controller.is_round_over() {
+            return SceneTransition::Push(Box::new(ResultsScene::new(self.controller.build_round_summary())));
+        }
+        SceneTransition::None
+    }
+    fn process_input(&mut self, input_manager: &InputManager) -> SceneTransition {
+        if input_manager.was_pressed_this_frame(InputSemantic::Pause) {
+            return SceneTransition::Push(Box::new(PauseScene::new(self.controller.get_settings())));
+        }
+        self.controller.process_input(input_manager);
+        SceneTransition::None
+    }
+    fn wants_text_input(&self) -> bool {
+        self.controller.wants_text_input()
+    }
+    fn get_input_context(&self) -> InputContextKind { InputContextKind::Gameplay }
+    fn process_text_input(&mut self, channel: &mut TextInputChannel) {
+        self.controller.process_text_input(channel);
+    }
+    fn draw(&mut self, canvas: &mut Canvas) {
+        self.controller.draw(canvas, &Transform::origin());
+    }
+    fn on_resume(&mut self) {
+        if self.controller.is_round_over() {
+            self.controller.restart_round();
+        }
+    }
+    fn handle_message(&mut self, message: SceneMessage) -> SceneTransition {
+        match message {
+            SceneMessage::RestartBoard => { self.controller.restart_board(); },
+            SceneMessage::ApplySettings(settings) => { self.controller.apply_settings(settings); },
+            SceneMessage::ReturnToMenu => { return SceneTransition::Pop; }
+        }
+        SceneTransition::None
+    }
+    fn resize(&mut self, viewport: &Viewport) {
+        self.controller.set_viewport(viewport);
+    }
+}
+
+pub struct ResultsScene {
+    root: Transform,
+    summary: RoundSummary
+}
+impl ResultsScene {
+    pub fn new(summary: RoundSummary) -> ResultsScene {
+        ResultsScene { root: Transform::anchored(&Viewport::reference(), 0.5, 0.5), summary }
+    }
+}
+impl Scene for ResultsScene {
+    fn process_input(&mut self, input_manager: &InputManager) -> SceneTransition {
+        if input_manager.was_pressed_this_frame(InputSemantic::Accept) ||
+            input_manager.was_pressed_this_frame(InputSemantic::Pointer)
+        {
+            return SceneTransition::Pop;
+        }
+        SceneTransition::None
+    }
+    fn draw(&mut self, canvas: &mut Canvas) {
+        self.summary.draw(canvas, &self.root);
+    }
+    fn resize(&mut self, viewport: &Viewport) {
+        self.root = Transform::anchored(viewport, 0.5, 0.5);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{cell::Cell, rc::Rc};
+
+    //Adds up the time it is updated for
+    struct ClockScene {
+        elapsed: Rc<Cell<f32>>
+    }
+    impl Scene for ClockScene {
+        fn update(&mut self, delta: f32) -> SceneTransition {
+            self.elapsed.set(self.elapsed.get()+delta);
+            SceneTransition::None
+        }
+        fn is_overlay(&self) -> bool { true }
+    }
+    fn clock_scene() -> (Box<dyn Scene>, Rc<Cell<f32>>) {
+        let elapsed = Rc::new(Cell::new(0.0));
+        (Box::new(ClockScene { elapsed: elapsed.clone() }), elapsed)
+    }
+
+    #[test]
+    fn scenes_under_an_overlay_stand_still() {
+        let mut stack = SceneStack::new();
+        let (game, game_time) = clock_scene();
+        let (pause, pause_time) = clock_scene();
+        stack.push(game);
+        stack.update(1.0);
+        stack.push(pause);
+        stack.update(5.0);
+        stack.update(5.0);
+        assert_eq!(game_time.get(), 1.0);
+        assert_eq!(pause_time.get(), 10.0);
+        stack.apply(SceneTransition::Pop);
+        stack.update(0.5);
+        assert_eq!(game_time.get(), 1.5); //Picks up where it was paused
+    }
+}
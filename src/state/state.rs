@@ -1,130 +1,205 @@
-use crate::util::input_manager;
-use crate::state::game_object;
-
-use std::ptr::eq;
+use crate::util::{input_manager, bindings::{Bindings, BINDINGS_FILE_NAME}, text_input::TextInputChannel, input_recording::{InputRecorder, InputReplay}, registry::Handle, viewport::Viewport};
+use crate::state::{game_object::{self, RenderText, Transform}, object_tree::ObjectTree, scene};
+use log::{info, warn};
 
 use ggez::{Context, GameError, GameResult,
+    event::{Axis, Button, GamepadId, winit_event::TouchPhase},
     input::keyboard::KeyInput,
     graphics::{
-        self, Canvas, Color
+        self, Color
     }
 };
 
-pub enum ObjectGroup {
-    Controllable,
-    General
-}
-pub struct RenderInstruction {
-    group: ObjectGroup,
-    index: usize,
-    depth: i32
-}
+pub type ObjectHandle = Handle<dyn game_object::GameObject>;
 
 pub struct GameState {
     input_manager: input_manager::InputManager,
-    objects: Vec<Box<dyn game_object::GameObject>>,
-    controllables: Vec<Box<dyn game_object::ControllableGameObject>>
+    text_input: TextInputChannel, //Typed text this frame, handed to whichever consumer has focus
+    scenes: scene::SceneStack,
+    objects: ObjectTree<dyn game_object::GameObject>, //Drawn over every scene, in window coordinates
+    recorder: Option<(InputRecorder, String)>, //Saved to the path when the game quits
+    replay: Option<InputReplay>, //Stands in for live input until it runs out
+    session_badge: Option<ObjectHandle> //Shows that input is being recorded or replayed
 }
 impl GameState {
     pub fn new() -> GameState {
         GameState {
-            input_manager: get_any4_input_manager(),
-            objects: Vec::new(),
-            controllables: Vec::new()
+            input_manager: input_manager::InputManager::from_bindings(&Bindings::load(BINDINGS_FILE_NAME)),
+            text_input: TextInputChannel::new(),
+            scenes: scene::SceneStack::new(),
+            objects: ObjectTree::new(),
+            recorder: None,
+            replay: None,
+            session_badge: None
         }
     }
-    fn collect_render_instructions(&self) -> Vec<RenderInstruction> {
-        let mut instructions = Vec::new();
-        let mut i: usize = 0;
-        while i < self.objects.len() {
-            let o = self.objects.get(i).expect("");
-            instructions.push(RenderInstruction { group: ObjectGroup::General, index: i, depth:  o.get_depth() });
-            i+=1;
+    /// Records every frame's input from now on. Call before the first update so the recording covers the whole session.
+    pub fn start_recording(&mut self, seed: u64, path: &str) {
+        let bindings = self.input_manager.get_bindings().expect("GameState's input manager is always built from bindings");
+        self.recorder = Some((InputRecorder::new(seed, bindings), path.to_string()));
+        self.set_session_badge(Some(("REC", Color::RED, path.to_string())));
+    }
+    /// Drives the game from the replay instead of live input, with the bindings it was recorded with.
+    /// Boards only come out the same if scenes were built with the replay's seed.
+    pub fn start_replay(&mut self, replay: InputReplay) {
+        self.input_manager = input_manager::InputManager::from_bindings(replay.get_bindings());
+        self.replay = Some(replay);
+        self.set_session_badge(Some(("REPLAY", Color::GREEN, "Live input returns when it ends".to_string())));
+    }
+    fn is_replaying(&self) -> bool {
+        self.replay.is_some()
+    }
+    /// Writes the bindings file, unless a replay is running: its bindings came from the recording, not the player.
+    fn save_bindings(&self, bindings: &Bindings) {
+        if self.is_replaying() {
+            info!("Not saving bindings changed during a replay");
+            return;
         }
-        let mut i: usize = 0;
-        while i < self.controllables.len() {
-            let o = self.controllables.get(i).expect("");
-            instructions.push(RenderInstruction { group: ObjectGroup::Controllable, index: i, depth: o.get_depth() });
-            i+=1;
+        if let Err(e) = bindings.save(BINDINGS_FILE_NAME) {
+            warn!("Could not save bindings: {}", e);
         }
-        instructions.sort_unstable_by(|a, b| a.depth.cmp(&b.depth));
-        return instructions;
     }
-    fn process_render_instruction(&mut self, instruction: RenderInstruction, canvas: &mut Canvas) {
-        match instruction.group {
-            ObjectGroup::Controllable => {
-                let o = self.controllables.get_mut(instruction.index).expect("");
-                o.draw(canvas);
-            },
-            ObjectGroup::General => {
-                let o = self.objects.get_mut(instruction.index).expect("");
-                o.draw(canvas);
-            }
+    /// A label in the corner with a line of detail hanging under it. Removing the label takes the detail with it.
+    fn set_session_badge(&mut self, badge: Option<(&str, Color, String)>) {
+        if let Some(handle) = self.session_badge.take() {
+            self.remove_object(handle);
+        }
+        if let Some((label, color, detail)) = badge {
+            let label = self.add_object(Box::new(RenderText::new(12.0, 12.0, 0, label, color)));
+            let detail = self.add_object(Box::new(RenderText::new(0.0, 22.0, 0, &detail, Color::WHITE)));
+            self.set_parent(detail, Some(label));
+            self.session_badge = Some(label);
         }
     }
-    pub fn add_object(&mut self, obj: Box<dyn game_object::GameObject>) -> &Box<dyn game_object::GameObject> {
-        let idx = self.objects.len();
-        self.objects.push(obj);
-        return self.objects.get(idx).unwrap();
+    pub fn push_scene(&mut self, scene: Box<dyn scene::Scene>) {
+        self.scenes.push(scene);
     }
-    pub fn remove_object(&mut self, obj: &Box<dyn game_object::GameObject>) {
-        let index = self.objects.iter().position(|x| eq(x, obj));
-        match index {
-            Some(idx) => { 
-                self.objects.remove(idx);
-            },
-            None => {}
-        }
+    /// Makes `child` move with `parent`. See `ObjectTree::set_parent`.
+    pub fn set_parent(&mut self, child: ObjectHandle, parent: Option<ObjectHandle>) {
+        self.objects.set_parent(child, parent);
     }
-    pub fn add_controllable(&mut self, obj: Box<dyn game_object::ControllableGameObject>) -> &Box<dyn game_object::ControllableGameObject> {
-        let idx = self.controllables.len();
-        self.controllables.push(obj);
-        return self.controllables.get(idx).unwrap();
+    /// Hands this frame's typed text to the top scene if it wants it. Nobody else sees it.
+    fn route_text_input(&mut self) {
+        if self.scenes.wants_text_input() {
+            self.scenes.process_text_input(&mut self.text_input);
+        }
     }
-    pub fn remove_controllable(&mut self, obj: &Box<dyn game_object::ControllableGameObject>) {
-        let index = self.controllables.iter().position(|x| eq(x, obj));
-        match index {
-            Some(idx) => { 
-                self.objects.remove(idx);
-            },
-            None => {}
+    /// Lines the input contexts up with the scenes that are now on the stack, plus text entry for whoever will
+    /// get text focus, so presses arriving before the next update already go through the right mappings.
+    fn sync_input_contexts(&mut self) {
+        let mut contexts = self.scenes.get_input_contexts();
+        if self.scenes.wants_text_input() {
+            contexts.push(input_manager::InputContextKind::TextEntry);
         }
+        self.input_manager.sync_contexts(&contexts);
+    }
+    pub fn add_object(&mut self, obj: Box<dyn game_object::GameObject>) -> ObjectHandle {
+        self.objects.insert(obj)
+    }
+    /// The object (and everything parented to it) stays alive until the end of the current frame.
+    pub fn remove_object(&mut self, handle: ObjectHandle) {
+        self.objects.remove(handle);
     }
 }
 impl ggez::event::EventHandler<GameError> for GameState {
     fn update(&mut self, ctx: &mut Context) -> GameResult {
         const DESIRED_FPS: u32 = 60;
-        while ctx.time.check_update_time(DESIRED_FPS) {}
-        for o in self.controllables.as_mut_slice() {
-            o.process_input(&self.input_manager);
-            o.as_mut_game_object().update();
+        let mut ticks = 0;
+        while ctx.time.check_update_time(DESIRED_FPS) {
+            ticks += 1;
         }
-        for o in self.objects.as_mut_slice() {
-            o.update();
+        let mut delta = (ticks as f32)/(DESIRED_FPS as f32); //Time covered by this frame, in whole fixed steps
+        if let Some(replay) = &mut self.replay {
+            delta = replay.play_frame(&mut self.input_manager, &mut self.text_input);
+            //Lay out at the recorded size whatever the window ends up as, so pointer positions hit the same things
+            if let Some(viewport) = replay.get_viewport().filter(|v| *v != self.scenes.get_viewport()) {
+                self.scenes.resize(viewport);
+                let (width, height) = viewport.get_size();
+                if let Err(e) = ctx.gfx.set_drawable_size(width, height) {
+                    warn!("Could not resize the window to the recorded {}x{}: {}", width, height, e);
+                }
+            }
+            if replay.is_finished() {
+                info!("Replay finished; back to live input");
+                self.replay = None;
+                self.set_session_badge(None);
+                let (width, height) = ctx.gfx.drawable_size();
+                if width > 0.0 && height > 0.0 {
+                    self.scenes.resize(Viewport::new(width, height)); //Resizes were ignored while it played
+                }
+            }
         }
+        if !self.is_replaying() {
+            self.text_input.poll_clipboard(); //Pastes land in the frame their read finished, so recordings keep the text
+        }
+        if let Some((recorder, _)) = &mut self.recorder {
+            recorder.record_frame(delta, self.scenes.get_viewport(), &self.input_manager, &self.text_input);
+        }
+        let had_scenes = !self.scenes.is_empty();
+        self.input_manager.update(delta);
+        self.route_text_input();
+        self.scenes.process_input(&self.input_manager);
+        if let Some(bindings) = self.scenes.take_requested_bindings() {
+            self.save_bindings(&bindings);
+            let cursor = self.input_manager.get_cursor_position();
+            self.input_manager = input_manager::InputManager::from_bindings(&bindings);
+            self.input_manager.set_cursor_position(cursor.x, cursor.y);
+        }
+        self.scenes.update(delta);
+        if had_scenes && self.scenes.is_empty() {
+            ctx.request_quit(); //The last scene was popped; nothing left to show
+        }
+        self.objects.update(delta);
         self.input_manager.process_input();
+        self.text_input.clear();
+        self.objects.flush_removals();
+        self.sync_input_contexts();
         Ok(())
     }
     fn draw(&mut self, ctx: &mut Context) -> GameResult {
         let mut canvas = graphics::Canvas::from_frame(ctx, Color::from([0.1, 0.2, 0.3, 1.0]));
-        for i in self.collect_render_instructions() {
-            self.process_render_instruction(i, &mut canvas);
-        }
+        self.scenes.draw(&mut canvas);
+        self.objects.draw(&mut canvas, &Transform::origin());
         canvas.finish(ctx)?;
         Ok(())
     }
 
+    fn quit_event(&mut self, _ctx: &mut Context) -> Result<bool, GameError> {
+        if let Some((recorder, path)) = &self.recorder {
+            match recorder.save(path) {
+                Ok(_) => { info!("Saved input recording to {:?}", path); },
+                Err(e) => { warn!("Could not save input recording to {:?}: {}", path, e); }
+            }
+        }
+        Ok(false)
+    }
+
+    fn resize_event(&mut self, _ctx: &mut Context, width: f32, height: f32) -> Result<(), GameError> {
+        if self.is_replaying() { return Ok(()); } //The replay sets the size it was recorded at
+        if width > 0.0 && height > 0.0 { //Minimizing reports 0x0; keep the last layout instead
+            self.scenes.resize(Viewport::new(width, height));
+        }
+        Ok(())
+    }
+
     fn key_down_event(
         &mut self,
         _ctx: &mut Context,
         input: KeyInput,
         _repeated: bool
     ) -> Result<(), GameError> {
-        if !_repeated {
-            match input.keycode {
-                Some(key) => { self.input_manager.process_input_pressed(input_manager::InputType::Keyboard(key)); },
-                None => {}
-            }
+        if self.is_replaying() { return Ok(()); }
+        match input.keycode {
+            Some(key) => {
+                if self.scenes.wants_text_input() {
+                    self.text_input.push_key(key, input.mods); //Editing keys keep the OS's own key repeat
+                }
+                if !_repeated {
+                    let chord = input_manager::KeyChord::from_key_event(key, input.mods);
+                    self.input_manager.process_input_pressed(input_manager::InputType::Keyboard(chord));
+                }
+            },
+            None => {}
         }
         Ok(())
     }
@@ -133,13 +208,27 @@ impl ggez::event::EventHandler<GameError> for GameState {
         _ctx: &mut Context,
         input: KeyInput
     ) -> Result<(), GameError> {
+        if self.is_replaying() { return Ok(()); }
         match input.keycode {
-            Some(key) => { self.input_manager.process_input_released(input_manager::InputType::Keyboard(key)); },
+            Some(key) => {
+                let chord = input_manager::KeyChord::from_key_event(key, input.mods);
+                self.input_manager.process_input_released(input_manager::InputType::Keyboard(chord));
+            },
             None => {}
         }
         Ok(())
     }
 
+    fn text_input_event(
+        &mut self,
+        _ctx: &mut Context,
+        _character: char
+    ) -> Result<(), GameError> {
+        if self.is_replaying() { return Ok(()); }
+        self.text_input.push_character(_character);
+        Ok(())
+    }
+
     fn mouse_button_down_event(
         &mut self,
         _ctx: &mut Context,
@@ -147,6 +236,8 @@ impl ggez::event::EventHandler<GameError> for GameState {
         _x: f32,
         _y: f32,
     ) -> Result<(), GameError> {
+        if self.is_replaying() { return Ok(()); }
+        self.input_manager.set_cursor_position(_x, _y);
         self.input_manager.process_input_pressed(input_manager::InputType::Mouse(_button));
         Ok(())
     }
@@ -159,156 +250,68 @@ impl ggez::event::EventHandler<GameError> for GameState {
         _x: f32,
         _y: f32,
     ) -> Result<(), GameError> {
+        if self.is_replaying() { return Ok(()); }
+        self.input_manager.set_cursor_position(_x, _y);
         self.input_manager.process_input_released(input_manager::InputType::Mouse(_button));
         Ok(())
     }
-}
 
-fn get_any4_input_manager() -> input_manager::InputManager {
-    let mut manager = input_manager::InputManager::new();
-    /* Control initialization */
-    //Accept
-    manager.register_input(
-        input_manager::InputSemantic::Accept, 
-        Box::new(input_manager::KeyboardInputProcessor::new(
-            vec!(
-                ggez::input::keyboard::KeyCode::Return, 
-                ggez::input::keyboard::KeyCode::Space, 
-                ggez::input::keyboard::KeyCode::NumpadEnter
-            )
-        ))
-    );
-    manager.register_input(
-        input_manager::InputSemantic::Accept, 
-        Box::new(input_manager::MouseInputProcessor::new(
-            vec!(ggez::input::mouse::MouseButton::Left)
-        ))
-    );
-    //Back
-    manager.register_input(
-        input_manager::InputSemantic::Back, 
-        Box::new(input_manager::KeyboardInputProcessor::new(
-            vec!(ggez::input::keyboard::KeyCode::Back)
-        ))
-    );
-    manager.register_input(
-        input_manager::InputSemantic::Back, 
-        Box::new(input_manager::MouseInputProcessor::new(
-            vec!(ggez::input::mouse::MouseButton::Right)
-        ))
-    );
-    //Directional input
-    manager.register_input(
-        input_manager::InputSemantic::Up, 
-        Box::new(input_manager::KeyboardInputProcessor::new(
-            vec!(
-                ggez::input::keyboard::KeyCode::W,
-                ggez::input::keyboard::KeyCode::Up
-            )
-        ))
-    );
-    manager.register_input(
-        input_manager::InputSemantic::Down, 
-        Box::new(input_manager::KeyboardInputProcessor::new(
-            vec!(
-                ggez::input::keyboard::KeyCode::S, 
-                ggez::input::keyboard::KeyCode::Down
-            )
-        ))
-    );
-    manager.register_input(
-        input_manager::InputSemantic::Left, 
-        Box::new(input_manager::KeyboardInputProcessor::new(
-            vec!(
-                ggez::input::keyboard::KeyCode::A, 
-                ggez::input::keyboard::KeyCode::Left
-            )
-        ))
-    );
-    manager.register_input(
-        input_manager::InputSemantic::Right, 
-        Box::new(input_manager::KeyboardInputProcessor::new(
-            vec!(
-                ggez::input::keyboard::KeyCode::D, 
-                ggez::input::keyboard::KeyCode::Right
-            )
-        ))
-    );
-    //Hotkeys
-    manager.register_input(
-        input_manager::InputSemantic::Plus, 
-        Box::new(input_manager::KeyboardInputProcessor::new(
-            vec!(
-                ggez::input::keyboard::KeyCode::Plus,
-                ggez::input::keyboard::KeyCode::NumpadAdd,
-                ggez::input::keyboard::KeyCode::Equals
-            )
-        ))
-    );
-    manager.register_input(
-        input_manager::InputSemantic::Minus, 
-        Box::new(input_manager::KeyboardInputProcessor::new(
-            vec!(
-                ggez::input::keyboard::KeyCode::Minus,
-                ggez::input::keyboard::KeyCode::NumpadSubtract
-            )
-        ))
-    );
-    manager.register_input(
-        input_manager::InputSemantic::Multiply,
-        Box::new(input_manager::KeyboardInputProcessor::new(
-            vec!(
-                ggez::input::keyboard::KeyCode::X,
-                ggez::input::keyboard::KeyCode::Asterisk,
-                ggez::input::keyboard::KeyCode::NumpadMultiply,
-                ggez::input::keyboard::KeyCode::Key8
-            )
-        ))
-    );
-    manager.register_input(
-        input_manager::InputSemantic::Divide, 
-        Box::new(input_manager::KeyboardInputProcessor::new(
-            vec!(
-                ggez::input::keyboard::KeyCode::Slash,
-                ggez::input::keyboard::KeyCode::NumpadDivide
-            )
-        ))
-    );
-    manager.register_input(
-        input_manager::InputSemantic::Hotbar1,
-        Box::new(input_manager::KeyboardInputProcessor::new(
-            vec!(
-                ggez::input::keyboard::KeyCode::Numpad1,
-                ggez::input::keyboard::KeyCode::Key1
-            )
-        ))
-    );
-    manager.register_input(
-        input_manager::InputSemantic::Hotbar2,
-        Box::new(input_manager::KeyboardInputProcessor::new(
-            vec!(
-                ggez::input::keyboard::KeyCode::Numpad2,
-                ggez::input::keyboard::KeyCode::Key2
-            )
-        ))
-    );
-    manager.register_input(
-        input_manager::InputSemantic::Hotbar3,
-        Box::new(input_manager::KeyboardInputProcessor::new(
-            vec!(
-                ggez::input::keyboard::KeyCode::Numpad3,
-                ggez::input::keyboard::KeyCode::Key3
-            )
-        ))
-    );
-    manager.register_input(
-        input_manager::InputSemantic::Hotbar4,
-        Box::new(input_manager::KeyboardInputProcessor::new(
-            vec!(
-                ggez::input::keyboard::KeyCode::Numpad4,
-                ggez::input::keyboard::KeyCode::Key4
-            )
-        ))
-    );
-    return manager;
-}
\ No newline at end of file
+    fn mouse_motion_event(
+        &mut self,
+        _ctx: &mut Context,
+        _x: f32,
+        _y: f32,
+        _dx: f32,
+        _dy: f32
+    ) -> Result<(), GameError> {
+        if self.is_replaying() { return Ok(()); }
+        self.input_manager.set_cursor_position(_x, _y);
+        Ok(())
+    }
+
+    /// Taps and drags act like the left mouse button. Replaces ggez's own handling, which forwards them as mouse events.
+    fn touch_event(
+        &mut self,
+        _ctx: &mut Context,
+        phase: TouchPhase,
+        x: f64,
+        y: f64
+    ) -> Result<(), GameError> {
+        if self.is_replaying() { return Ok(()); }
+        self.input_manager.process_touch(phase, x as f32, y as f32);
+        Ok(())
+    }
+
+    //NOTE: every connected gamepad drives the same player, so the id is ignored
+    fn gamepad_button_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        _btn: Button,
+        _id: GamepadId
+    ) -> Result<(), GameError> {
+        if self.is_replaying() { return Ok(()); }
+        self.input_manager.process_input_pressed(input_manager::InputType::Gamepad(input_manager::GamepadInput::Button(_btn)));
+        Ok(())
+    }
+    fn gamepad_button_up_event(
+        &mut self,
+        _ctx: &mut Context,
+        _btn: Button,
+        _id: GamepadId
+    ) -> Result<(), GameError> {
+        if self.is_replaying() { return Ok(()); }
+        self.input_manager.process_input_released(input_manager::InputType::Gamepad(input_manager::GamepadInput::Button(_btn)));
+        Ok(())
+    }
+    fn gamepad_axis_event(
+        &mut self,
+        _ctx: &mut Context,
+        _axis: Axis,
+        _value: f32,
+        _id: GamepadId
+    ) -> Result<(), GameError> {
+        if self.is_replaying() { return Ok(()); }
+        self.input_manager.process_axis(_axis, _value);
+        Ok(())
+    }
+}
@@ -0,0 +1,162 @@
+use crate::util::animation::{Animation, Pose};
+use crate::state::game_object::{RenderText, Transform};
+use ggez::{
+    Context, GameResult,
+    graphics::{Canvas, Color, DrawMode, DrawParam, Mesh, Rect},
+    mint::Point2
+};
+use std::sync::OnceLock;
+
+pub const TILE_SIZE: f32 = 32.0;
+const TILE_CORNER_RADIUS: f32 = 6.0;
+const TILE_OUTLINE_WIDTH: f32 = 2.0;
+const ERROR_FLASH_SECONDS: f32 = 0.4;
+const DRAGGED_DEPTH: i32 = 100;
+
+const FILL_COLOR: Color = Color { r: 0.15, g: 0.22, b: 0.35, a: 1.0 };
+const OUTLINE_COLOR: Color = Color { r: 0.8, g: 0.8, b: 0.8, a: 1.0 };
+const EMPTY_OUTLINE_COLOR: Color = Color { r: 0.8, g: 0.8, b: 0.8, a: 0.5 };
+const CONSUMED_OUTLINE_COLOR: Color = Color { r: 0.8, g: 0.8, b: 0.8, a: 0.2 };
+const ERROR_COLOR: Color = Color { r: 0.9, g: 0.15, b: 0.15, a: 0.8 };
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TileState {
+    Empty, //A slot waiting for a value; drawn as an outline
+    Filled,
+    Selected, //The slot the player's next action applies to
+    Consumed //A hotbar value that has been used up
+}
+
+struct TileMeshes {
+    fill: Mesh,
+    outline: Mesh
+}
+//Meshes need the graphics context to be built, which objects don't have when they are created or drawn,
+//so a single white copy is built at startup and tinted per draw.
+static TILE_MESHES: OnceLock<TileMeshes> = OnceLock::new();
+
+/// Builds the shared tile meshes. Until this is called tiles only draw their text.
+pub fn init_tile_meshes(ctx: &Context) -> GameResult {
+    let bounds = Rect::new(-TILE_SIZE/2.0, -TILE_SIZE/2.0, TILE_SIZE, TILE_SIZE);
+    let meshes = TileMeshes {
+        fill: Mesh::new_rounded_rectangle(ctx, DrawMode::fill(), bounds, TILE_CORNER_RADIUS, Color::WHITE)?,
+        outline: Mesh::new_rounded_rectangle(ctx, DrawMode::stroke(TILE_OUTLINE_WIDTH), bounds, TILE_CORNER_RADIUS, Color::WHITE)?
+    };
+    let _ = TILE_MESHES.set(meshes); //Already built is fine
+    Ok(())
+}
+
+/// A rounded square slot with a label centered in it. Positioned by its center.
+pub struct Tile {
+    transform: Transform,
+    state: TileState,
+    label: RenderText,
+    text_color: Color,
+    error_flash: f32, //Seconds of error highlight left
+    animation: Option<Animation>,
+    drag_position: Option<Point2<f32>> //Drawn here instead while the pointer carries it
+}
+impl Tile {
+    pub fn new(x: f32, y: f32, depth: i32, text_color: Color) -> Tile {
+        let mut label = RenderText::new(0.0, 0.0, 0, "", text_color);
+        label.set_centered();
+        Tile {
+            transform: Transform::new(x, y, depth),
+            state: TileState::Empty,
+            label,
+            text_color,
+            error_flash: 0.0,
+            animation: None,
+            drag_position: None
+        }
+    }
+    pub fn set_text(&mut self, text: String) {
+        self.label.set_text(text);
+    }
+    pub fn set_state(&mut self, state: TileState) {
+        self.state = state;
+    }
+    pub fn flash_error(&mut self) {
+        self.error_flash = ERROR_FLASH_SECONDS;
+    }
+    /// Replaces whatever animation was playing. Poses scale about the tile's center.
+    pub fn play_animation(&mut self, animation: Animation) {
+        self.animation = Some(animation);
+    }
+    pub fn is_animating(&self) -> bool {
+        self.animation.is_some()
+    }
+    /// Draws the tile centered on `position` (in the same space as its own position) until set back to None.
+    /// Hit-testing keeps using the resting position.
+    pub fn set_drag_position(&mut self, position: Option<Point2<f32>>) {
+        self.drag_position = position;
+    }
+    pub fn is_dragged(&self) -> bool {
+        self.drag_position.is_some()
+    }
+    pub fn get_position(&self) -> Point2<f32> {
+        self.transform.get_position()
+    }
+    /// Dragged tiles are lifted above the rest of the board.
+    pub fn get_depth(&self) -> i32 {
+        match self.is_dragged() {
+            true => self.transform.get_depth()+DRAGGED_DEPTH,
+            false => self.transform.get_depth()
+        }
+    }
+    /// Whether `point` (in window coordinates) is over the tile's resting position.
+    pub fn contains_point(&self, point: Point2<f32>, parent: &Transform) -> bool {
+        let world = self.transform.compose(parent);
+        let center = world.get_position();
+        let half = world.get_scale()*TILE_SIZE/2.0;
+        (point.x-center.x).abs() <= half && (point.y-center.y).abs() <= half
+    }
+    pub fn update(&mut self, delta: f32) {
+        self.error_flash = (self.error_flash-delta).max(0.0);
+        if let Some(animation) = &mut self.animation {
+            animation.advance(delta);
+            if animation.is_finished() { self.animation = None; }
+        }
+    }
+    pub fn draw(&mut self, canvas: &mut Canvas, parent: &Transform) {
+        let pose = match &self.animation {
+            Some(animation) => animation.get_pose(),
+            None => Pose::rest()
+        };
+        let base = match self.drag_position {
+            Some(position) => Transform::new(position.x, position.y, self.transform.get_depth()),
+            None => self.transform
+        };
+        let world = Transform::posed(pose.x, pose.y, pose.scale).compose(&base.compose(parent));
+        if let Some(meshes) = TILE_MESHES.get() {
+            let param = DrawParam::new().dest(world.get_position()).scale([world.get_scale(), world.get_scale()]);
+            match self.state {
+                TileState::Empty => {
+                    canvas.draw(&meshes.outline, param.color(tint(EMPTY_OUTLINE_COLOR, &pose)));
+                },
+                TileState::Filled => {
+                    canvas.draw(&meshes.fill, param.color(tint(FILL_COLOR, &pose)));
+                    canvas.draw(&meshes.outline, param.color(tint(OUTLINE_COLOR, &pose)));
+                },
+                TileState::Selected => {
+                    canvas.draw(&meshes.fill, param.color(tint(FILL_COLOR, &pose)));
+                    canvas.draw(&meshes.outline, param.color(tint(Color::YELLOW, &pose)));
+                },
+                TileState::Consumed => {
+                    canvas.draw(&meshes.outline, param.color(tint(CONSUMED_OUTLINE_COLOR, &pose)));
+                }
+            }
+            if self.error_flash > 0.0 {
+                let mut flash = ERROR_COLOR;
+                flash.a *= self.error_flash/ERROR_FLASH_SECONDS; //Fades out
+                canvas.draw(&meshes.fill, param.color(tint(flash, &pose)));
+            }
+        }
+        self.label.set_text_color(tint(self.text_color, &pose));
+        self.label.draw(canvas, &world);
+    }
+}
+
+fn tint(color: Color, pose: &Pose) -> Color {
+    Color::new(color.r*pose.color[0], color.g*pose.color[1], color.b*pose.color[2], color.a*pose.color[3])
+}
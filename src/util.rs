@@ -1,2 +1,14 @@
 pub mod input_manager;
-pub mod data_generator;
\ No newline at end of file
+pub mod bindings;
+pub mod data_generator;
+pub mod expression_parser;
+pub mod solver;
+pub mod scoring;
+pub mod timer;
+pub mod registry;
+pub mod viewport;
+pub mod layout;
+pub mod animation;
+pub mod clipboard;
+pub mod text_input;
+pub mod input_recording;
\ No newline at end of file
@@ -0,0 +1,121 @@
+use keyframe::{
+    AnimationSequence, CanTween, EasingFunction, Keyframe,
+    functions::{Linear, EaseIn, EaseOut, EaseInOut, EaseOutCubic},
+    num_traits::Float
+};
+
+/* #region Public Interface */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+    EaseOutCubic
+}
+
+/// What happens to gameplay input that arrives while an animation is playing.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AnimationInputPolicy {
+    Block, //Dropped
+    Queue //Replayed in order once the animations finish
+}
+
+/// Everything an animation can change about an object, applied on top of where and how the object would
+/// normally be drawn: an offset from its resting position, a scale, and a color multiplier (the last channel is alpha).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pose {
+    pub x: f32,
+    pub y: f32,
+    pub scale: f32,
+    pub color: [f32; 4]
+}
+impl Pose {
+    /// The object exactly as it would be drawn without any animation.
+    pub fn rest() -> Pose {
+        Pose { x: 0.0, y: 0.0, scale: 1.0, color: [1.0, 1.0, 1.0, 1.0] }
+    }
+    pub fn offset(x: f32, y: f32) -> Pose {
+        Pose { x, y, ..Pose::rest() }
+    }
+    pub fn with_scale(mut self, scale: f32) -> Pose {
+        self.scale = scale;
+        self
+    }
+    pub fn with_color(mut self, color: [f32; 4]) -> Pose {
+        self.color = color;
+        self
+    }
+    pub fn with_alpha(mut self, alpha: f32) -> Pose {
+        self.color[3] = alpha;
+        self
+    }
+}
+impl Default for Pose {
+    fn default() -> Pose { Pose::rest() }
+}
+impl CanTween for Pose {
+    fn ease(from: Self, to: Self, time: impl Float) -> Self {
+        Pose {
+            x: f32::ease(from.x, to.x, time),
+            y: f32::ease(from.y, to.y, time),
+            scale: f32::ease(from.scale, to.scale, time),
+            color: <[f32; 4]>::ease(from.color, to.color, time)
+        }
+    }
+}
+
+/// A sequence of poses over time, built segment by segment:
+/// `Animation::new(start).then(pose, seconds, easing).then(...)`.
+pub struct Animation {
+    keyframes: Vec<(Pose, f64, Easing)>, //Each easing applies to the segment leaving that keyframe
+    sequence: AnimationSequence<Pose>
+}
+impl Animation {
+    pub fn new(start: Pose) -> Animation {
+        let mut animation = Animation { keyframes: vec!((start, 0.0, Easing::Linear)), sequence: AnimationSequence::new() };
+        animation.rebuild();
+        animation
+    }
+    /// Appends a segment that moves from the previous pose to `pose` over `duration` seconds.
+    pub fn then(mut self, pose: Pose, duration: f32, easing: Easing) -> Animation {
+        let last = self.keyframes.last_mut().expect("Animation always has a starting keyframe");
+        last.2 = easing;
+        let time = last.1+(duration.max(0.0) as f64);
+        self.keyframes.push((pose, time, Easing::Linear));
+        self.rebuild();
+        self
+    }
+    pub fn advance(&mut self, delta: f32) {
+        self.sequence.advance_by(delta as f64);
+    }
+    pub fn get_pose(&self) -> Pose {
+        self.sequence.now()
+    }
+    pub fn is_finished(&self) -> bool {
+        self.sequence.finished()
+    }
+}
+/* #endregion */
+
+/* #region Secret Inner-Workings */
+impl Easing {
+    fn to_function(self) -> Box<dyn EasingFunction + Send + Sync> {
+        match self {
+            Easing::Linear => Box::new(Linear),
+            Easing::EaseIn => Box::new(EaseIn),
+            Easing::EaseOut => Box::new(EaseOut),
+            Easing::EaseInOut => Box::new(EaseInOut),
+            Easing::EaseOutCubic => Box::new(EaseOutCubic)
+        }
+    }
+}
+impl Animation {
+    fn rebuild(&mut self) {
+        let keyframes: Vec<Keyframe<Pose>> = self.keyframes.iter()
+            .map(|(pose, time, easing)| Keyframe::new_dynamic(*pose, *time, easing.to_function()))
+            .collect();
+        self.sequence = AnimationSequence::from(keyframes);
+    }
+}
+/* #endregion */
@@ -0,0 +1,397 @@
+use crate::util::input_manager::{GamepadInput, InputSemantic, InputType, KeyChord};
+use ggez::{event::{Axis, Button}, input::{keyboard::{KeyCode, KeyMods}, mouse::MouseButton}};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::{collections::{BTreeMap, HashMap}, fs, path::Path};
+
+/* #region Public Interface */
+pub const BINDINGS_FILE_NAME: &str = "rsc/config/bindings.json";
+//Bumped whenever new defaults should reach files saved before them. Files without a version are 1.
+pub const BINDINGS_FILE_VERSION: u32 = 2;
+
+//Every semantic a player can rebind, in the order the controls screen lists them
+pub const REBINDABLE_SEMANTICS: &'static [InputSemantic] = &[
+    InputSemantic::Accept, InputSemantic::Back, InputSemantic::Pointer,
+    InputSemantic::Up, InputSemantic::Down, InputSemantic::Left, InputSemantic::Right,
+    InputSemantic::Hotbar1, InputSemantic::Hotbar2, InputSemantic::Hotbar3, InputSemantic::Hotbar4,
+    InputSemantic::Plus, InputSemantic::Minus, InputSemantic::Multiply, InputSemantic::Divide,
+    InputSemantic::ToggleExpressionEntry, InputSemantic::Hint, InputSemantic::Skip, InputSemantic::Pause
+];
+
+/// Which keys and buttons trigger each semantic. Stored as JSON with the file version and a map of semantic names to input names,
+/// e.g. `{"version": 2, "bindings": {"Accept": ["Key:Return", "Key:Space", "Pad:Start"], "Multiply": ["Key:Shift+Key8"]}}`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bindings {
+    map: HashMap<InputSemantic, Vec<InputType>>
+}
+impl Bindings {
+    /// The game's built-in controls. On a gamepad the face buttons pick operators, the shoulders and triggers
+    /// pick hotbar numbers left to right, and the d-pad or left stick navigates.
+    pub fn defaults() -> Bindings {
+        let mut bindings = Bindings { map: HashMap::new() };
+        bindings.set(InputSemantic::Accept, vec!(key(KeyCode::Return), key(KeyCode::Space), key(KeyCode::NumpadEnter)));
+        bindings.set(InputSemantic::Pointer, vec!(InputType::Mouse(MouseButton::Left)));
+        bindings.set(InputSemantic::Back, vec!(key(KeyCode::Back), InputType::Mouse(MouseButton::Right)));
+        bindings.set(InputSemantic::Up, vec!(key(KeyCode::W), key(KeyCode::Up)));
+        bindings.set(InputSemantic::Down, vec!(key(KeyCode::S), key(KeyCode::Down)));
+        bindings.set(InputSemantic::Left, vec!(key(KeyCode::A), key(KeyCode::Left)));
+        bindings.set(InputSemantic::Right, vec!(key(KeyCode::D), key(KeyCode::Right)));
+        bindings.set(InputSemantic::Plus, vec!(key(KeyCode::Plus), key(KeyCode::NumpadAdd), key(KeyCode::Equals)));
+        bindings.set(InputSemantic::Minus, vec!(key(KeyCode::Minus), key(KeyCode::NumpadSubtract)));
+        bindings.set(InputSemantic::Multiply, vec!(key(KeyCode::X), key(KeyCode::Asterisk), key(KeyCode::NumpadMultiply), chord(KeyCode::Key8, KeyMods::SHIFT)));
+        bindings.set(InputSemantic::Divide, vec!(key(KeyCode::Slash), key(KeyCode::NumpadDivide)));
+        bindings.set(InputSemantic::Hotbar1, vec!(key(KeyCode::Numpad1), key(KeyCode::Key1)));
+        bindings.set(InputSemantic::Hotbar2, vec!(key(KeyCode::Numpad2), key(KeyCode::Key2)));
+        bindings.set(InputSemantic::Hotbar3, vec!(key(KeyCode::Numpad3), key(KeyCode::Key3)));
+        bindings.set(InputSemantic::Hotbar4, vec!(key(KeyCode::Numpad4), key(KeyCode::Key4)));
+        bindings.set(InputSemantic::ToggleExpressionEntry, vec!(key(KeyCode::Tab)));
+        bindings.set(InputSemantic::Hint, vec!(key(KeyCode::H)));
+        bindings.set(InputSemantic::Skip, vec!(key(KeyCode::Delete)));
+        bindings.set(InputSemantic::Pause, vec!(key(KeyCode::Escape)));
+        bindings.add_gamepad_defaults();
+        bindings
+    }
+    /// Reads the bindings file, writing the defaults there first if it does not exist yet. Files from older versions are
+    /// written back once migrated, so they only go through `migrate` the first time.
+    /// Semantics the file leaves out keep their default inputs; a file that can't be read falls back to the defaults entirely.
+    pub fn load(path: &str) -> Bindings {
+        if !Path::new(path).is_file() {
+            let defaults = Bindings::defaults();
+            if let Err(e) = defaults.save(path) {
+                warn!("Could not write default bindings: {}", e);
+            }
+            return defaults;
+        }
+        match fs::read_to_string(path).map_err(|e| e.to_string()).and_then(|contents| Bindings::from_json(&contents)) {
+            Ok((bindings, version)) => {
+                if version < BINDINGS_FILE_VERSION {
+                    if let Err(e) = bindings.save(path) {
+                        warn!("Could not write updated bindings: {}", e);
+                    }
+                }
+                bindings
+            },
+            Err(e) => {
+                warn!("Could not read bindings from {:?}, using the defaults: {}", path, e);
+                Bindings::defaults()
+            }
+        }
+    }
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        if let Some(dir) = Path::new(path).parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        fs::write(path, self.to_json()?).map_err(|e| e.to_string())
+    }
+    /// Files from older versions are brought up to date with `migrate`.
+    /// Also returns the version the file was saved as.
+    pub fn from_json(contents: &str) -> Result<(Bindings, u32), String> {
+        let (version, names) = match serde_json::from_str(contents).map_err(|e| e.to_string())? {
+            StoredBindings::Versioned(file) => (file.version, file.bindings),
+            StoredBindings::Unversioned(names) => (1, names)
+        };
+        let mut bindings = Bindings::from_names(&names);
+        if version < BINDINGS_FILE_VERSION {
+            info!("Updating bindings from version {} to {}", version, BINDINGS_FILE_VERSION);
+            bindings.migrate(version);
+        }
+        Ok((bindings, version))
+    }
+    pub fn to_json(&self) -> Result<String, String> {
+        let file = BindingsFile { version: BINDINGS_FILE_VERSION, bindings: self.to_names() };
+        serde_json::to_string_pretty(&file).map_err(|e| e.to_string())
+    }
+    /// Input names by semantic name, as stored in the bindings file. Semantics left out keep their defaults.
+    pub fn from_names(names: &BTreeMap<String, Vec<String>>) -> Bindings {
+        let mut bindings = Bindings::defaults();
+        for (semantic_name, input_names) in names.iter() {
+            let semantic = match REBINDABLE_SEMANTICS.iter().find(|s| get_semantic_name(**s) == *semantic_name) {
+                Some(s) => *s,
+                None => {
+                    warn!("Ignoring bindings for unknown input semantic {:?}", semantic_name);
+                    continue;
+                }
+            };
+            let mut inputs = Vec::new();
+            for input_name in input_names {
+                match get_input_from_name(input_name) {
+                    Some(input) => { inputs.push(input); },
+                    None => { warn!("Ignoring unknown input {:?} bound to {:?}", input_name, semantic_name); }
+                }
+            }
+            bindings.set(semantic, inputs);
+        }
+        bindings
+    }
+    pub fn to_names(&self) -> BTreeMap<String, Vec<String>> {
+        let mut names: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for semantic in REBINDABLE_SEMANTICS {
+            names.insert(get_semantic_name(*semantic), self.get_inputs(*semantic).iter().map(|i| get_input_name(*i)).collect());
+        }
+        names
+    }
+    pub fn get_inputs(&self, semantic: InputSemantic) -> &[InputType] {
+        match self.map.get(&semantic) {
+            Some(inputs) => inputs.as_slice(),
+            None => &[]
+        }
+    }
+    pub fn set(&mut self, semantic: InputSemantic, inputs: Vec<InputType>) {
+        self.map.insert(semantic, inputs);
+    }
+    /// Every other semantic that `input` already triggers.
+    pub fn find_conflicts(&self, semantic: InputSemantic, input: InputType) -> Vec<InputSemantic> {
+        REBINDABLE_SEMANTICS.iter()
+            .copied()
+            .filter(|s| *s != semantic && self.get_inputs(*s).contains(&input))
+            .collect()
+    }
+    /// Makes `input` the semantic's key (or mouse or gamepad button, for those inputs), replacing the ones it had of that kind.
+    /// The input is taken away from any other semantic that used it.
+    pub fn rebind(&mut self, semantic: InputSemantic, input: InputType) {
+        for (_, inputs) in self.map.iter_mut() {
+            inputs.retain(|i| *i != input);
+        }
+        let mut inputs: Vec<InputType> = self.get_inputs(semantic).iter().copied().filter(|i| !is_same_device(*i, input)).collect();
+        inputs.insert(0, input);
+        self.set(semantic, inputs);
+    }
+}
+
+/// Name used in the bindings file, e.g. "Accept".
+pub fn get_semantic_name(semantic: InputSemantic) -> String {
+    format!("{:?}", semantic)
+}
+/// Name used in the bindings file, e.g. "Key:Return", "Key:Ctrl+Z", "Mouse:Left", "Pad:South" or "Pad:LeftStickX-".
+/// "Touch" only shows up in input recordings, since touch can't be rebound.
+/// Keyboard chords list their modifiers, then the key held first, then the key pressed, so "Key:A+S" means S while A is held.
+pub fn get_input_name(input: InputType) -> String {
+    match input {
+        InputType::Keyboard(chord) => format!("Key:{}", get_chord_name(&chord)),
+        InputType::Mouse(MouseButton::Other(id)) => format!("Mouse:{}", id),
+        InputType::Mouse(button) => format!("Mouse:{:?}", button),
+        InputType::Gamepad(GamepadInput::Button(button)) => format!("Pad:{:?}", button),
+        InputType::Gamepad(GamepadInput::AxisPositive(axis)) => format!("Pad:{:?}+", axis),
+        InputType::Gamepad(GamepadInput::AxisNegative(axis)) => format!("Pad:{:?}-", axis),
+        InputType::Touch => "Touch".to_string()
+    }
+}
+pub fn get_input_from_name(name: &str) -> Option<InputType> {
+    match name.split_once(':') {
+        Some(("Key", chord_name)) => get_chord_from_name(chord_name).map(InputType::Keyboard),
+        Some(("Mouse", "Left")) => Some(InputType::Mouse(MouseButton::Left)),
+        Some(("Mouse", "Right")) => Some(InputType::Mouse(MouseButton::Right)),
+        Some(("Mouse", "Middle")) => Some(InputType::Mouse(MouseButton::Middle)),
+        Some(("Mouse", id)) => id.parse().ok().map(|id| InputType::Mouse(MouseButton::Other(id))),
+        Some(("Pad", pad_name)) => get_gamepad_input_from_name(pad_name).map(InputType::Gamepad),
+        None if name == "Touch" => Some(InputType::Touch),
+        _ => None
+    }
+}
+/* #endregion */
+
+/* #region Secret Inner-Workings */
+#[derive(Serialize, Deserialize)]
+struct BindingsFile {
+    version: u32,
+    bindings: BTreeMap<String, Vec<String>>
+}
+//Version 1 files are the bare map
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredBindings {
+    Versioned(BindingsFile),
+    Unversioned(BTreeMap<String, Vec<String>>)
+}
+
+impl Bindings {
+    /// Adds the defaults introduced after `version`. Version 1 files list every semantic, so without this they would
+    /// never get the gamepad defaults or Shift+8 for multiply. Inputs the player already uses elsewhere are left alone.
+    fn migrate(&mut self, version: u32) {
+        let mut added = Bindings { map: HashMap::new() };
+        if version < 2 {
+            added.add_gamepad_defaults();
+            added.map.entry(InputSemantic::Multiply).or_default().push(chord(KeyCode::Key8, KeyMods::SHIFT));
+        }
+        for semantic in REBINDABLE_SEMANTICS {
+            for input in added.get_inputs(*semantic) {
+                if !self.get_inputs(*semantic).contains(input) && self.find_conflicts(*semantic, *input).is_empty() {
+                    self.map.entry(*semantic).or_default().push(*input);
+                }
+            }
+        }
+    }
+    fn add_gamepad_defaults(&mut self) {
+        let pad_defaults = [
+            (InputSemantic::Plus, vec!(pad(Button::South))),
+            (InputSemantic::Minus, vec!(pad(Button::East))),
+            (InputSemantic::Multiply, vec!(pad(Button::West))),
+            (InputSemantic::Divide, vec!(pad(Button::North))),
+            (InputSemantic::Hotbar1, vec!(pad(Button::LeftTrigger2))),
+            (InputSemantic::Hotbar2, vec!(pad(Button::LeftTrigger))),
+            (InputSemantic::Hotbar3, vec!(pad(Button::RightTrigger))),
+            (InputSemantic::Hotbar4, vec!(pad(Button::RightTrigger2))),
+            (InputSemantic::Up, vec!(pad(Button::DPadUp), InputType::Gamepad(GamepadInput::AxisPositive(Axis::LeftStickY)))),
+            (InputSemantic::Down, vec!(pad(Button::DPadDown), InputType::Gamepad(GamepadInput::AxisNegative(Axis::LeftStickY)))),
+            (InputSemantic::Left, vec!(pad(Button::DPadLeft), InputType::Gamepad(GamepadInput::AxisNegative(Axis::LeftStickX)))),
+            (InputSemantic::Right, vec!(pad(Button::DPadRight), InputType::Gamepad(GamepadInput::AxisPositive(Axis::LeftStickX)))),
+            (InputSemantic::Accept, vec!(pad(Button::Start))),
+            (InputSemantic::Back, vec!(pad(Button::Select))),
+            (InputSemantic::Hint, vec!(pad(Button::LeftThumb))),
+            (InputSemantic::Pause, vec!(pad(Button::Mode)))
+        ];
+        for (semantic, inputs) in pad_defaults {
+            self.map.entry(semantic).or_default().extend(inputs);
+        }
+    }
+}
+fn key(code: KeyCode) -> InputType {
+    InputType::Keyboard(KeyChord::new(code))
+}
+fn chord(code: KeyCode, mods: KeyMods) -> InputType {
+    InputType::Keyboard(KeyChord::new(code).with_mods(mods))
+}
+fn get_chord_name(chord: &KeyChord) -> String {
+    let mut parts: Vec<String> = MODIFIER_NAMES.iter()
+        .filter(|m| chord.mods.contains(m.1))
+        .map(|m| m.0.to_string())
+        .collect();
+    if let Some(held) = chord.held {
+        parts.push(get_key_name(held));
+    }
+    parts.push(get_key_name(chord.key));
+    parts.join("+")
+}
+fn get_chord_from_name(name: &str) -> Option<KeyChord> {
+    let mut parts: Vec<&str> = name.split('+').collect();
+    let key = get_key_from_name(parts.pop()?)?;
+    let mut chord = KeyChord::new(key);
+    for part in parts {
+        if let Some(modifier) = MODIFIER_NAMES.iter().find(|m| m.0 == part) {
+            chord.mods.insert(modifier.1);
+        }
+        else if chord.held.is_none() {
+            chord = chord.with_held(get_key_from_name(part)?);
+        }
+        else {
+            return None; //Only one other key can be held
+        }
+    }
+    Some(chord)
+}
+fn get_key_name(key: KeyCode) -> String {
+    format!("{:?}", key)
+}
+fn get_key_from_name(name: &str) -> Option<KeyCode> {
+    KEY_NAMES.iter().find(|k| k.0 == name).map(|k| k.1)
+}
+fn pad(button: Button) -> InputType {
+    InputType::Gamepad(GamepadInput::Button(button))
+}
+fn is_same_device(a: InputType, b: InputType) -> bool {
+    matches!((a, b),
+        (InputType::Keyboard(_), InputType::Keyboard(_)) |
+        (InputType::Mouse(_), InputType::Mouse(_)) |
+        (InputType::Gamepad(_), InputType::Gamepad(_))
+    )
+}
+fn get_gamepad_input_from_name(name: &str) -> Option<GamepadInput> {
+    if let Some(axis_name) = name.strip_suffix('+') {
+        return AXIS_NAMES.iter().find(|a| a.0 == axis_name).map(|a| GamepadInput::AxisPositive(a.1));
+    }
+    if let Some(axis_name) = name.strip_suffix('-') {
+        return AXIS_NAMES.iter().find(|a| a.0 == axis_name).map(|a| GamepadInput::AxisNegative(a.1));
+    }
+    BUTTON_NAMES.iter().find(|b| b.0 == name).map(|b| GamepadInput::Button(b.1))
+}
+const BUTTON_NAMES: &'static [(&'static str, Button)] = &[
+    ("South", Button::South), ("East", Button::East), ("North", Button::North), ("West", Button::West),
+    ("C", Button::C), ("Z", Button::Z),
+    ("LeftTrigger", Button::LeftTrigger), ("LeftTrigger2", Button::LeftTrigger2),
+    ("RightTrigger", Button::RightTrigger), ("RightTrigger2", Button::RightTrigger2),
+    ("Select", Button::Select), ("Start", Button::Start), ("Mode", Button::Mode),
+    ("LeftThumb", Button::LeftThumb), ("RightThumb", Button::RightThumb),
+    ("DPadUp", Button::DPadUp), ("DPadDown", Button::DPadDown), ("DPadLeft", Button::DPadLeft), ("DPadRight", Button::DPadRight)
+];
+const AXIS_NAMES: &'static [(&'static str, Axis)] = &[
+    ("LeftStickX", Axis::LeftStickX), ("LeftStickY", Axis::LeftStickY), ("LeftZ", Axis::LeftZ),
+    ("RightStickX", Axis::RightStickX), ("RightStickY", Axis::RightStickY), ("RightZ", Axis::RightZ),
+    ("DPadX", Axis::DPadX), ("DPadY", Axis::DPadY)
+];
+const MODIFIER_NAMES: &'static [(&'static str, KeyMods)] = &[
+    ("Ctrl", KeyMods::CTRL), ("Alt", KeyMods::ALT), ("Shift", KeyMods::SHIFT), ("Logo", KeyMods::LOGO)
+];
+//Keys that can be named in the bindings file. Names match the key codes' Debug output, which is what gets written.
+const KEY_NAMES: &'static [(&'static str, KeyCode)] = &[
+    ("Key1", KeyCode::Key1), ("Key2", KeyCode::Key2), ("Key3", KeyCode::Key3), ("Key4", KeyCode::Key4), ("Key5", KeyCode::Key5),
+    ("Key6", KeyCode::Key6), ("Key7", KeyCode::Key7), ("Key8", KeyCode::Key8), ("Key9", KeyCode::Key9), ("Key0", KeyCode::Key0),
+    ("A", KeyCode::A), ("B", KeyCode::B), ("C", KeyCode::C), ("D", KeyCode::D), ("E", KeyCode::E), ("F", KeyCode::F),
+    ("G", KeyCode::G), ("H", KeyCode::H), ("I", KeyCode::I), ("J", KeyCode::J), ("K", KeyCode::K), ("L", KeyCode::L),
+    ("M", KeyCode::M), ("N", KeyCode::N), ("O", KeyCode::O), ("P", KeyCode::P), ("Q", KeyCode::Q), ("R", KeyCode::R),
+    ("S", KeyCode::S), ("T", KeyCode::T), ("U", KeyCode::U), ("V", KeyCode::V), ("W", KeyCode::W), ("X", KeyCode::X),
+    ("Y", KeyCode::Y), ("Z", KeyCode::Z),
+    ("F1", KeyCode::F1), ("F2", KeyCode::F2), ("F3", KeyCode::F3), ("F4", KeyCode::F4), ("F5", KeyCode::F5), ("F6", KeyCode::F6),
+    ("F7", KeyCode::F7), ("F8", KeyCode::F8), ("F9", KeyCode::F9), ("F10", KeyCode::F10), ("F11", KeyCode::F11), ("F12", KeyCode::F12),
+    ("Escape", KeyCode::Escape), ("Insert", KeyCode::Insert), ("Home", KeyCode::Home), ("Delete", KeyCode::Delete),
+    ("End", KeyCode::End), ("PageDown", KeyCode::PageDown), ("PageUp", KeyCode::PageUp),
+    ("Left", KeyCode::Left), ("Up", KeyCode::Up), ("Right", KeyCode::Right), ("Down", KeyCode::Down),
+    ("Back", KeyCode::Back), ("Return", KeyCode::Return), ("Space", KeyCode::Space), ("Tab", KeyCode::Tab),
+    ("Numpad0", KeyCode::Numpad0), ("Numpad1", KeyCode::Numpad1), ("Numpad2", KeyCode::Numpad2), ("Numpad3", KeyCode::Numpad3),
+    ("Numpad4", KeyCode::Numpad4), ("Numpad5", KeyCode::Numpad5), ("Numpad6", KeyCode::Numpad6), ("Numpad7", KeyCode::Numpad7),
+    ("Numpad8", KeyCode::Numpad8), ("Numpad9", KeyCode::Numpad9),
+    ("NumpadAdd", KeyCode::NumpadAdd), ("NumpadSubtract", KeyCode::NumpadSubtract), ("NumpadMultiply", KeyCode::NumpadMultiply),
+    ("NumpadDivide", KeyCode::NumpadDivide), ("NumpadDecimal", KeyCode::NumpadDecimal), ("NumpadEnter", KeyCode::NumpadEnter),
+    ("Apostrophe", KeyCode::Apostrophe), ("Asterisk", KeyCode::Asterisk), ("Backslash", KeyCode::Backslash),
+    ("Comma", KeyCode::Comma), ("Equals", KeyCode::Equals), ("Grave", KeyCode::Grave), ("LBracket", KeyCode::LBracket),
+    ("Minus", KeyCode::Minus), ("Period", KeyCode::Period), ("Plus", KeyCode::Plus), ("RBracket", KeyCode::RBracket),
+    ("Semicolon", KeyCode::Semicolon), ("Slash", KeyCode::Slash),
+    ("LShift", KeyCode::LShift), ("RShift", KeyCode::RShift), ("LControl", KeyCode::LControl), ("RControl", KeyCode::RControl),
+    ("LAlt", KeyCode::LAlt), ("RAlt", KeyCode::RAlt)
+];
+/* #endregion */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_bindings_load_unchanged() {
+        let mut bindings = Bindings::defaults();
+        bindings.rebind(InputSemantic::Hint, key(KeyCode::J));
+        bindings.set(InputSemantic::Skip, Vec::new());
+        assert_eq!(Bindings::from_json(&bindings.to_json().expect("")), Ok((bindings, BINDINGS_FILE_VERSION)));
+    }
+
+    #[test]
+    fn unversioned_files_get_newer_defaults() {
+        let bindings = Bindings::from_json(r#"{"Multiply": ["Key:X"], "Plus": ["Key:Plus"], "Up": ["Key:W"]}"#).expect("").0;
+        assert_eq!(bindings.get_inputs(InputSemantic::Multiply), &[key(KeyCode::X), pad(Button::West), chord(KeyCode::Key8, KeyMods::SHIFT)]);
+        assert_eq!(bindings.get_inputs(InputSemantic::Plus), &[key(KeyCode::Plus), pad(Button::South)]);
+        assert!(bindings.get_inputs(InputSemantic::Up).contains(&InputType::Gamepad(GamepadInput::AxisPositive(Axis::LeftStickY))));
+    }
+
+    #[test]
+    fn migration_leaves_inputs_in_use_alone() {
+        let bindings = Bindings::from_json(r#"{"Multiply": ["Key:X"], "Hint": ["Key:Shift+Key8", "Pad:West"]}"#).expect("").0;
+        assert_eq!(bindings.get_inputs(InputSemantic::Multiply), &[key(KeyCode::X)]);
+        assert_eq!(bindings.get_inputs(InputSemantic::Hint), &[chord(KeyCode::Key8, KeyMods::SHIFT), pad(Button::West), pad(Button::LeftThumb)]);
+    }
+
+    #[test]
+    fn current_files_are_not_migrated() {
+        let json = format!(r#"{{"version": {}, "bindings": {{"Multiply": ["Key:X"]}}}}"#, BINDINGS_FILE_VERSION);
+        let bindings = Bindings::from_json(&json).expect("").0;
+        assert_eq!(bindings.get_inputs(InputSemantic::Multiply), &[key(KeyCode::X)]);
+    }
+
+    #[test]
+    fn loading_an_old_file_rewrites_it_once() {
+        let path = std::env::temp_dir().join(format!("bindings-migration-{}.json", std::process::id()));
+        let path = path.to_str().expect("");
+        fs::write(path, r#"{"Multiply": ["Key:X"]}"#).expect("");
+        let migrated = Bindings::load(path);
+        let contents = fs::read_to_string(path).expect("");
+        fs::remove_file(path).expect("");
+        assert_eq!(Bindings::from_json(&contents), Ok((migrated, BINDINGS_FILE_VERSION)));
+    }
+}
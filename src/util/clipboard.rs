@@ -0,0 +1,133 @@
+use std::{io::Write, process::{Command, Stdio}, sync::mpsc::{self, Receiver, Sender}, thread};
+
+/* #region Public Interface */
+/// The system clipboard, reached through the first of the platform's clipboard tools that works. The tools run on a
+/// worker thread, so copying and pasting never hold up a frame; reads come back through `take_pasted` a frame or more later.
+/// Tools that aren't installed are only looked for once, and after one has worked the others are no longer tried.
+pub struct Clipboard {
+    worker: Option<(Sender<Request>, Receiver<Option<String>>)> //Started on first use
+}
+impl Clipboard {
+    pub fn new() -> Clipboard {
+        Clipboard { worker: None }
+    }
+    /// Asks for the text on the clipboard. It shows up in `take_pasted` once the tool has answered.
+    pub fn request_text(&mut self) {
+        self.send(Request::Paste);
+    }
+    /// Puts `text` on the clipboard, if any of the tools will take it.
+    pub fn set_text(&mut self, text: &str) {
+        self.send(Request::Copy(text.to_string()));
+    }
+    /// Every read that has finished since the last call, oldest first. None where no tool could read the clipboard,
+    /// or it held no text.
+    pub fn take_pasted(&mut self) -> Vec<Option<String>> {
+        match &self.worker {
+            Some((_, pasted)) => pasted.try_iter().collect(),
+            None => Vec::new()
+        }
+    }
+}
+/* #endregion */
+
+/* #region Secret Inner-Workings */
+enum Request {
+    Copy(String),
+    Paste
+}
+
+//Which of a list of commands to run
+struct CommandCache {
+    working: Option<usize>, //The first one that succeeded; it is the only one run from then on
+    missing: Vec<bool> //Failed to start, so not installed
+}
+impl CommandCache {
+    fn new(commands: &[(&str, &[&str])]) -> CommandCache {
+        CommandCache { working: None, missing: vec![false; commands.len()] }
+    }
+    //`run` returns None if the command could not be started at all, or whether it succeeded and what it gave back
+    fn run<T>(&mut self, commands: &[(&str, &[&str])], mut run: impl FnMut(&mut Command) -> Option<Option<T>>) -> Option<T> {
+        if let Some(i) = self.working {
+            let (program, args) = commands[i];
+            return run(Command::new(program).args(args)).flatten();
+        }
+        for (i, (program, args)) in commands.iter().enumerate() {
+            if self.missing[i] { continue; }
+            match run(Command::new(program).args(*args)) {
+                Some(Some(result)) => {
+                    self.working = Some(i);
+                    return Some(result);
+                },
+                Some(None) => {},
+                None => { self.missing[i] = true; }
+            }
+        }
+        None
+    }
+}
+
+impl Clipboard {
+    fn send(&mut self, request: Request) {
+        let (requests, _) = self.worker.get_or_insert_with(start_worker);
+        if requests.send(request).is_err() {
+            self.worker = None; //The worker is gone; the next request starts another
+        }
+    }
+}
+fn start_worker() -> (Sender<Request>, Receiver<Option<String>>) {
+    let (request_sender, requests) = mpsc::channel();
+    let (pasted_sender, pasted) = mpsc::channel();
+    thread::spawn(move || {
+        let mut copy_commands = CommandCache::new(COPY_COMMANDS);
+        let mut paste_commands = CommandCache::new(PASTE_COMMANDS);
+        for request in requests {
+            match request {
+                Request::Copy(text) => { copy_commands.run(COPY_COMMANDS, |command| write_to(command, &text)); },
+                Request::Paste => {
+                    if pasted_sender.send(paste_commands.run(PASTE_COMMANDS, read_from)).is_err() { return; }
+                }
+            }
+        }
+    });
+    (request_sender, pasted)
+}
+fn read_from(command: &mut Command) -> Option<Option<String>> {
+    let output = command.stdin(Stdio::null()).stderr(Stdio::null()).output().ok()?;
+    if !output.status.success() { return Some(None); }
+    Some(String::from_utf8(output.stdout).ok())
+}
+fn write_to(command: &mut Command, text: &str) -> Option<Option<()>> {
+    //Some of these stay in the background to serve the clipboard, so nothing waits on their output
+    let mut child = command.stdin(Stdio::piped()).stdout(Stdio::null()).stderr(Stdio::null()).spawn().ok()?;
+    let written = match child.stdin.take() {
+        Some(mut stdin) => stdin.write_all(text.as_bytes()).is_ok(),
+        None => false
+    };
+    Some((child.wait().is_ok_and(|status| status.success()) && written).then_some(()))
+}
+//Tried in order; Wayland first, since X tools also run there through XWayland but don't reach native windows
+#[cfg(all(unix, not(target_os = "macos")))]
+const COPY_COMMANDS: &'static [(&'static str, &'static [&'static str])] = &[
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard", "-in"]),
+    ("xsel", &["--clipboard", "--input"])
+];
+#[cfg(all(unix, not(target_os = "macos")))]
+const PASTE_COMMANDS: &'static [(&'static str, &'static [&'static str])] = &[
+    ("wl-paste", &["--no-newline"]),
+    ("xclip", &["-selection", "clipboard", "-out"]),
+    ("xsel", &["--clipboard", "--output"])
+];
+#[cfg(target_os = "macos")]
+const COPY_COMMANDS: &'static [(&'static str, &'static [&'static str])] = &[("pbcopy", &[])];
+#[cfg(target_os = "macos")]
+const PASTE_COMMANDS: &'static [(&'static str, &'static [&'static str])] = &[("pbpaste", &[])];
+#[cfg(windows)]
+const COPY_COMMANDS: &'static [(&'static str, &'static [&'static str])] = &[("clip", &[])];
+#[cfg(windows)]
+const PASTE_COMMANDS: &'static [(&'static str, &'static [&'static str])] = &[("powershell", &["-NoProfile", "-Command", "Get-Clipboard"])];
+#[cfg(not(any(unix, windows)))]
+const COPY_COMMANDS: &'static [(&'static str, &'static [&'static str])] = &[];
+#[cfg(not(any(unix, windows)))]
+const PASTE_COMMANDS: &'static [(&'static str, &'static [&'static str])] = &[];
+/* #endregion */
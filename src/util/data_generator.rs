@@ -22,6 +22,7 @@ use serde::{
     }
 };
 use itertools::Itertools;
+use rand::{Rng, rngs::StdRng};
 use fraction::{Fraction, Decimal};
 use queues::{self, IsQueue};
 
@@ -37,6 +38,26 @@ pub enum OpType {
     Divide,
     None
 }
+impl OpType {
+    pub fn apply(&self, left: Fraction, right: Fraction) -> Option<Fraction> {
+        match self {
+            OpType::Plus => Some(left+right),
+            OpType::Minus => Some(left-right),
+            OpType::Multiply => Some(left*right),
+            OpType::Divide => Some(left/right),
+            OpType::None => None
+        }
+    }
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            OpType::Plus => "+",
+            OpType::Minus => "-",
+            OpType::Multiply => "x",
+            OpType::Divide => "/",
+            OpType::None => ""
+        }
+    }
+}
 
 pub type TargetValidatorFunc = fn(f32) -> bool;
 pub struct SetConfig {
@@ -59,6 +80,12 @@ impl SetConfig {
             difficulties
         }
     }
+    pub fn set_difficulties(&mut self, difficulties: Vec<InputDifficulty>) {
+        self.difficulties = difficulties;
+    }
+    pub fn get_difficulties(&self) -> &Vec<InputDifficulty> {
+        &self.difficulties
+    }
 }
 #[derive(Debug, Clone)]
 pub struct Board {
@@ -66,12 +93,7 @@ pub struct Board {
     pub target: Fraction,
     pub difficulty: InputDifficulty
 }
-impl Board {
-    pub fn get_board_info(&self) -> String {
-        format!("Input Vector: {:?}, Target Value: {}, Difficulty Rating: {}", self.input, self.target.to_string(), difficulty_to_string(self.difficulty))
-    }
-}
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum InputDifficulty {
     Easy,
     Moderate,
@@ -107,7 +129,7 @@ impl DifficultyPools {
             }
         }
         for pool in priority_vec {
-            if pool.len() > 0 { return Ok(pool); }
+            if !pool.is_empty() { return Ok(pool); }
         }
         return Err("All pools empty. Something's wrong!".to_string());
         
@@ -136,24 +158,24 @@ pub fn init(min_val: i32, max_val: i32, input_size: usize, force_regen: bool) {
     if force_regen || !verify_data_integrity() { populate_output_directory(min_val, max_val+1, input_size) }
 }
 pub fn get_deserialized_input_data_pool_map() -> Result<HashMap<String, DifficultyPools>, String>  {
-    if verify_data_integrity() == false { return Err("Data integrity could not be verified. Input data could not be deserialized.".to_string()); }
+    if !verify_data_integrity() { return Err("Data integrity could not be verified. Input data could not be deserialized.".to_string()); }
     let contents = std::fs::read_to_string(INPUT_FILE_NAME).expect("");
     let json_structure: HashMap<String, DifficultyPools> = serde_json::from_str(&contents).expect("");
     return Ok(json_structure);
 }
-pub fn get_set_of_inputs(mut pool_map: HashMap<String, DifficultyPools>, config: &SetConfig) -> Result<Vec<Board>, String> { //returns an error if the set could not be created to spec
+pub fn get_set_of_inputs(mut pool_map: HashMap<String, DifficultyPools>, config: &SetConfig, rng: &mut StdRng) -> Result<Vec<Board>, String> { //returns an error if the set could not be created to spec; the same rng state always gives the same set
     let mut result_vector = Vec::new();
     pool_map = remove_inputs_without_matching_difficulties(pool_map, &config.difficulties);
     while result_vector.len() < config.size {
-        let &difficulty = config.difficulties.get(rand::random::<usize>()%config.difficulties.len()).expect("");
+        let &difficulty = config.difficulties.get(rng.gen::<usize>()%config.difficulties.len()).expect("");
         let mut target = config.target.clone();
-        if config.target == "" { 
-            match get_random_viable_target(&pool_map, config.validator) {
+        if config.target.is_empty() { 
+            match get_random_viable_target(&pool_map, config.validator, rng) {
                 Ok(val) => {target = val},
                 Err(e) => { return Err(e) } //Error implies this is impossible given input constraints, so we return
             }
         } 
-        match get_input_of_difficulty_for_target(&pool_map, target.as_str(), difficulty) {
+        match get_input_of_difficulty_for_target(&pool_map, target.as_str(), difficulty, rng) {
             Ok((input, index, pool_difficulty)) => {
                 let map = pool_map.get_mut(target.as_str()).expect("");
                 match pool_difficulty {
@@ -168,7 +190,7 @@ pub fn get_set_of_inputs(mut pool_map: HashMap<String, DifficultyPools>, config:
                     }
                 }
                 //NOTE: if &target is "inf", this will panic
-                let mut target_as_fraction;
+                let target_as_fraction;
                 match Fraction::from_str(&target) {
                     Ok(tar) => { 
                         target_as_fraction = tar;
@@ -177,14 +199,14 @@ pub fn get_set_of_inputs(mut pool_map: HashMap<String, DifficultyPools>, config:
                         target_as_fraction = Fraction::from(1)/Fraction::from(0);
                     }
                 }
-                result_vector.push(Board{ target: target_as_fraction, input: input, difficulty: pool_difficulty } );                
+                result_vector.push(Board{ target: target_as_fraction, input, difficulty: pool_difficulty } );                
             },
             Err(e) => {
                 //we found no viable inputs for the given target
-                if config.target != "" { return Err(e); } //Since the target is fixed, this set has become impossible to create
+                if !config.target.is_empty() { return Err(e); } //Since the target is fixed, this set has become impossible to create
                 else { //Else it's probably still possible with some other target
                     pool_map.remove(&target); //So let's remove the target with no inputs
-                    if pool_map.keys().len() <= 0 { return Err(e) } //And if that made our data set empty, then it means we cannot finish generating this input
+                    if pool_map.is_empty() { return Err(e) } //And if that made our data set empty, then it means we cannot finish generating this input
                     //Note that this works because the error branch is only ever reached when NO pool is populated, irrespective of difficulty specifier.
                 } 
             }
@@ -205,7 +227,7 @@ impl Serialize for SerializableFraction {
         S: Serializer,
     {
         let mut state = serializer.serialize_struct("SerializableFraction", 1)?;
-        state.serialize_field("fraction", &self.fraction.to_string()).expect(&format!("Serialization of fraction {:?} failed", self.fraction));
+        state.serialize_field("fraction", &self.fraction.to_string()).unwrap_or_else(|_| panic!("Serialization of fraction {:?} failed", self.fraction));
         state.end()
     }
 }
@@ -250,7 +272,7 @@ impl<'de> Deserialize<'de> for SerializableFraction {
                 V: SeqAccess<'de>,
             {
                 let frac = seq.next_element()?.ok_or_else(|| de::Error::invalid_length(0, &self))?;
-                Ok(SerializableFraction{ fraction: Fraction::from_str(frac).expect(&format!("Unable to deserialize fraction {:?}", frac)) })
+                Ok(SerializableFraction{ fraction: Fraction::from_str(frac).unwrap_or_else(|_| panic!("Unable to deserialize fraction {:?}", frac)) })
             }
             fn visit_map<V>(self, mut map: V) -> Result<SerializableFraction, V::Error>
             where 
@@ -268,7 +290,7 @@ impl<'de> Deserialize<'de> for SerializableFraction {
                     }
                 }
                 let frac = frac.ok_or_else(|| de::Error::missing_field("fraction"))?;
-                Ok(SerializableFraction{ fraction: Fraction::from_str(frac).expect(&format!("Unable to deserialize fraction {:?}", frac)) })
+                Ok(SerializableFraction{ fraction: Fraction::from_str(frac).unwrap_or_else(|_| panic!("Unable to deserialize fraction {:?}", frac)) })
             }
         }
         const FIELDS: &'static [&'static str] = &["fraction"];
@@ -294,12 +316,12 @@ impl OperationMarker {
     }
     fn sum_input_vector(&self) -> Fraction {
         let mut acc = Fraction::from(0);
-        if self.input_vector.len() <= 0 { return acc; };
+        if self.input_vector.is_empty() { return acc; };
         let mut sum_queue: queues::Queue<Fraction> = queues::Queue::new();
         for v in self.input_vector.as_slice() { sum_queue.add(v.fraction).expect(""); }
         
         while sum_queue.size() > 0 {
-            acc = acc+sum_queue.remove().expect("");
+            acc += sum_queue.remove().expect("");
         }
         return acc;
     }
@@ -332,7 +354,7 @@ impl ops::Add<FrequencyCounter> for FrequencyCounter {
                     *entry += *v;
                 },
                 None => {
-                    self.map.entry(*k).or_insert(v.clone());
+                    self.map.entry(*k).or_insert(*v);
                 }
             }
         }
@@ -346,22 +368,15 @@ fn verify_data_integrity() -> bool {
     }
     return true;
 }
-fn difficulty_to_string(rating: InputDifficulty) -> String {
-    match rating {
-        InputDifficulty::Easy => {"Easy".to_string()},
-        InputDifficulty::Moderate =>{"Moderate".to_string()},
-        InputDifficulty::Hard =>{"Hard".to_string()}
-    }
-}
 fn remove_inputs_without_matching_difficulties(pool_map: HashMap<String, DifficultyPools>, valid_difficulties: &Vec<InputDifficulty>) -> HashMap<String, DifficultyPools> {
     let mut new_pool_map = HashMap::new();
     for (k, v) in pool_map.iter() {
         let mut add = false;
         for diff in valid_difficulties {
             match diff {
-                InputDifficulty::Easy => { if v.easy.len() > 0 { add = true }},
-                InputDifficulty::Moderate => {if v.moderate.len() > 0 { add = true }},
-                InputDifficulty::Hard => {if v.hard.len() > 0 { add = true }}
+                InputDifficulty::Easy => { if !v.easy.is_empty() { add = true }},
+                InputDifficulty::Moderate => {if !v.moderate.is_empty() { add = true }},
+                InputDifficulty::Hard => {if !v.hard.is_empty() { add = true }}
             }
         }
         if add {
@@ -371,24 +386,25 @@ fn remove_inputs_without_matching_difficulties(pool_map: HashMap<String, Difficu
     return new_pool_map;
 }
 
-fn get_random_viable_target(pool_map: &HashMap<String, DifficultyPools>, validator: Option<TargetValidatorFunc>) -> Result<String, String> {
+fn get_random_viable_target(pool_map: &HashMap<String, DifficultyPools>, validator: Option<TargetValidatorFunc>, rng: &mut StdRng) -> Result<String, String> {
     let mut keys: Vec<String> = pool_map.keys().cloned().collect();
-    let mut idx = rand::random::<usize>()%keys.len();
+    keys.sort(); //HashMap order changes between runs, which would make seeded sets differ
+    let mut idx = rng.gen::<usize>()%keys.len();
     let local_keys = keys.clone();
     let mut k = local_keys.get(idx).expect("");
     let mut k_as_f32: f32 = Decimal::from_str(k).unwrap().to_string().parse::<f32>().unwrap(); //there might be a more efficient way to do this
     match validator {
         Some(v_func) => {
-            while v_func(k_as_f32) == false {
+            while !v_func(k_as_f32) {
                 keys.remove(idx);
-                idx = rand::random::<usize>()%&keys.as_slice().len();
+                idx = rng.gen::<usize>()%keys.as_slice().len();
                 k = keys.get(idx).expect("");
                 k_as_f32 = Decimal::from_str(k).unwrap().to_string().parse::<f32>().unwrap();
             }
         },
         None => {}
     }
-    if keys.len() <= 0 { return Err("Ran out of viable targets...".to_string())}
+    if keys.is_empty() { return Err("Ran out of viable targets...".to_string())}
     return Ok(k.to_string());
 }
 
@@ -399,12 +415,12 @@ fn populate_output_directory(min_val: i32, max_val: i32, combination_count: usiz
     write_to_file(INPUT_FILE_NAME, "Failed to create difficulty pools file!", serde_json::to_string(&input_ranking_map).expect("Something went wrong when trying to write difficulty pools file").as_bytes());
 }
 
-fn get_input_of_difficulty_for_target(pool_map: &HashMap<String, DifficultyPools>, target: &str, difficulty: InputDifficulty) -> Result<(Vec<i32>, usize, InputDifficulty), String> {
+fn get_input_of_difficulty_for_target(pool_map: &HashMap<String, DifficultyPools>, target: &str, difficulty: InputDifficulty, rng: &mut StdRng) -> Result<(Vec<i32>, usize, InputDifficulty), String> {
     match pool_map.get(target) {
         Some(difficulty_pools) => {
             match difficulty_pools.get_closest_matching_populated_pool(difficulty) {
                 Ok(pool) => {
-                    let idx: usize = rand::random::<usize>()%(pool.len());
+                    let idx: usize = rng.gen::<usize>()%(pool.len());
                     return Ok((pool.get(idx).expect("").clone(), idx, difficulty_pools.get_difficulty_of_pool(pool).expect("")));
                 },
                 Err(e) => {
@@ -471,20 +487,20 @@ fn rank_input(input: &UnidirectionalNode) -> Vec<InputRanking> {
         else if occurrences > 2 && occurrences <= 5 {
             difficulty = InputDifficulty::Moderate;
         }
-        input_ranking.push(InputRanking{target: k.clone(), difficulty, input: input.data.input_vector_to_i32().expect("Input ranking failed.") })
+        input_ranking.push(InputRanking{target: *k, difficulty, input: input.data.input_vector_to_i32().expect("Input ranking failed.") })
     }
     return input_ranking;
 }
 
-fn get_pairs(input_vector: &Vec<SerializableFraction>) -> Vec<FractionalPair> {
+fn get_pairs(input_vector: &[SerializableFraction]) -> Vec<FractionalPair> {
     let mut index = 0;
     let mut vector = Vec::new();
     while index < input_vector.len()-1 {
-        let first = input_vector.get(index).unwrap().clone();
+        let first = *input_vector.get(index).unwrap();
         let mut i = 0;
         while i < input_vector.len() {
             if i != index {
-                let second = input_vector.get(i).unwrap().clone();
+                let second = *input_vector.get(i).unwrap();
                 vector.push(FractionalPair{ first: first.fraction, second: second.fraction });
             }
             i+=1;
@@ -519,7 +535,7 @@ fn check_value_in_vec<T: Clone + PartialEq>(v: Vec<T>, val: T) -> bool {
 fn count_leaf_instances_of(node: &UnidirectionalNode) -> FrequencyCounter {
     let mut map = FrequencyCounter{ map: HashMap::new() };
     for c in node.children.clone() {
-        if c.children.len() <= 0 {
+        if c.children.is_empty() {
             let value = c.data.sum_input_vector();
             match map.map.get_mut(&value) {
                 Some(entry) => {
@@ -542,7 +558,7 @@ fn generate_total_possibility_space(min_val: i32, max_val: i32, combination_coun
     let combinations = (min_val..max_val).combinations_with_replacement(combination_count);
     let mut root_nodes = Vec::new();
     for c in combinations {
-        let input_vec: Vec<_> = c.iter().map(|i| *i).collect();
+        let input_vec: Vec<_> = c.to_vec();
         root_nodes.push(get_serializable_root_node(input_vec));
     }
     return root_nodes;
@@ -560,7 +576,7 @@ fn get_serializable_root_node(input_vector: Vec<i32>) -> UnidirectionalNode {
     return UnidirectionalNode{ data: root.clone(), children: convert_child_nodes_to_json(&tree, tree.root().unwrap().node_id()) };
 }
 
-fn convert_child_nodes_to_json(tree: &Box<Tree<OperationMarker>>, root_id: NodeId) -> Vec<UnidirectionalNode> {
+fn convert_child_nodes_to_json(tree: &Tree<OperationMarker>, root_id: NodeId) -> Vec<UnidirectionalNode> {
     let children = tree.get(root_id).unwrap().children();
     let mut child_vec = Vec::new();
     for c in children {
@@ -587,9 +603,9 @@ fn generate_tree_for_input(input_vector: Vec<i32>) -> Box<Tree<OperationMarker>>
 }
 
 fn generate_decision_tree(tree: &mut Tree<OperationMarker>, node_id: NodeId, input_vector: &Vec<SerializableFraction>) {
-    let pairs = get_pairs(&input_vector);
+    let pairs = get_pairs(input_vector);
     for p in pairs {
-        compute_operations(tree, node_id, p.first, p.second, remove_pair(&input_vector, p));
+        compute_operations(tree, node_id, p.first, p.second, remove_pair(input_vector, p));
     }
 }
 
@@ -599,14 +615,14 @@ fn compute_operations(tree: &mut Tree<OperationMarker>, node_id: NodeId, first_i
     vector.push(compute_single_op(tree, node_id, first_input, second_input, remaining_inputs.clone(), compute_minus, OpType::Minus));
     vector.push(compute_single_op(tree, node_id, first_input, second_input, remaining_inputs.clone(), compute_times, OpType::Multiply));
     vector.push(compute_single_op(tree, node_id, first_input, second_input, remaining_inputs.clone(), compute_divided_by, OpType::Divide));
-    if remaining_inputs.len() > 0 {
+    if !remaining_inputs.is_empty() {
         for v in vector {
             generate_decision_tree(tree, v.0, &v.1);
         }
     }
 }
 type Operation = fn(Fraction, Fraction) -> SerializableFraction;
-fn compute_single_op<'a>(tree: &'a mut Tree<OperationMarker>, node_id: NodeId, first_input: Fraction, second_input: Fraction, remaining_inputs: Vec<SerializableFraction>, op_function: Operation, op_type: OpType) -> (NodeId, Vec<SerializableFraction>) {
+fn compute_single_op(tree: &mut Tree<OperationMarker>, node_id: NodeId, first_input: Fraction, second_input: Fraction, remaining_inputs: Vec<SerializableFraction>, op_function: Operation, op_type: OpType) -> (NodeId, Vec<SerializableFraction>) {
     let mut vector = Vec::new();
     let mut node = tree.get_mut(node_id).unwrap();
     let mut r = remaining_inputs;
@@ -629,4 +645,37 @@ fn compute_divided_by(first_input: Fraction, second_input: Fraction) -> Serializ
     SerializableFraction{ fraction: first_input/second_input }
 }
 
-/* #endregion */
\ No newline at end of file
+/* #endregion */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    fn sample_pool_map() -> HashMap<String, DifficultyPools> {
+        let mut pool_map = HashMap::new();
+        for (target, base) in [("10", 1), ("12", 2), ("24", 3)] {
+            let mut pools = DifficultyPools::new();
+            for i in 0..3 {
+                pools.easy.push(vec!(base, base+i, 1, 2));
+                pools.moderate.push(vec!(base, base+i, 3, 4));
+                pools.hard.push(vec!(base, base+i, 5, 6));
+            }
+            pool_map.insert(target.to_string(), pools);
+        }
+        pool_map
+    }
+    fn deal(seed: u64) -> Vec<(Vec<i32>, Fraction, InputDifficulty)> {
+        let config = SetConfig::new(5, None, None, vec!(InputDifficulty::Easy, InputDifficulty::Moderate, InputDifficulty::Hard));
+        let boards = get_set_of_inputs(sample_pool_map(), &config, &mut StdRng::seed_from_u64(seed)).expect("");
+        boards.into_iter().map(|b| (b.input, b.target, b.difficulty)).collect()
+    }
+
+    #[test]
+    fn same_seed_deals_same_set() {
+        let first = deal(1234);
+        assert_eq!(first.len(), 5);
+        for _ in 0..5 {
+            assert_eq!(deal(1234), first);
+        }
+    }
+}
@@ -0,0 +1,271 @@
+use std::fmt;
+use fraction::Fraction;
+use crate::util::data_generator::OpType;
+
+/* #region Public Interface */
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExpressionNode {
+    Number(i32),
+    Operation(OpType, Box<ExpressionNode>, Box<ExpressionNode>)
+}
+impl ExpressionNode {
+    pub fn evaluate(&self) -> Result<Fraction, ExpressionError> {
+        match self {
+            ExpressionNode::Number(n) => { Ok(Fraction::from(*n)) },
+            ExpressionNode::Operation(op_type, left, right) => {
+                let l = left.evaluate()?;
+                let r = right.evaluate()?;
+                if *op_type == OpType::Divide && r == Fraction::from(0) {
+                    return Err(ExpressionError::new("Division by zero", None));
+                }
+                match op_type.apply(l, r) {
+                    Some(val) => Ok(val),
+                    None => Err(ExpressionError::new("Missing operator", None))
+                }
+            }
+        }
+    }
+    pub fn collect_numbers(&self) -> Vec<i32> {
+        let mut numbers = Vec::new();
+        self.collect_numbers_into(&mut numbers);
+        return numbers;
+    }
+    fn collect_numbers_into(&self, numbers: &mut Vec<i32>) {
+        match self {
+            ExpressionNode::Number(n) => { numbers.push(*n); },
+            ExpressionNode::Operation(_, left, right) => {
+                left.collect_numbers_into(numbers);
+                right.collect_numbers_into(numbers);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpressionError {
+    pub message: String,
+    pub position: Option<usize> //Character index into the source text, if the error can be pinned to one
+}
+impl ExpressionError {
+    fn new(message: &str, position: Option<usize>) -> ExpressionError {
+        ExpressionError { message: message.to_string(), position }
+    }
+}
+impl fmt::Display for ExpressionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.position {
+            Some(pos) => write!(f, "{} (at column {})", self.message, pos+1),
+            None => write!(f, "{}", self.message)
+        }
+    }
+}
+
+pub fn parse_expression(text: &str) -> Result<ExpressionNode, ExpressionError> {
+    let tokens = tokenize(text)?;
+    let mut parser = Parser { tokens, index: 0, end: text.chars().count() };
+    let node = parser.parse_sum()?;
+    match parser.peek() {
+        Some(token) => Err(ExpressionError::new(&format!("Unexpected '{}'", token.kind), Some(token.position))),
+        None => Ok(node)
+    }
+}
+/// Parses and evaluates `text`, requiring every value in `inputs` to appear exactly once.
+pub fn evaluate_with_inputs(text: &str, inputs: &Vec<i32>) -> Result<Fraction, ExpressionError> {
+    let node = parse_expression(text)?;
+    let mut used = node.collect_numbers();
+    let mut expected = inputs.clone();
+    used.sort();
+    expected.sort();
+    if used != expected {
+        return Err(ExpressionError::new(&format!("Each of {:?} must be used exactly once", inputs), None));
+    }
+    node.evaluate()
+}
+/* #endregion */
+
+/* #region Secret Inner-Workings */
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Number(i32),
+    Operator(OpType),
+    OpenParen,
+    CloseParen
+}
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TokenKind::Number(n) => write!(f, "{}", n),
+            TokenKind::Operator(op) => write!(f, "{}", op.symbol()),
+            TokenKind::OpenParen => write!(f, "("),
+            TokenKind::CloseParen => write!(f, ")")
+        }
+    }
+}
+struct Token {
+    kind: TokenKind,
+    position: usize
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, ExpressionError> {
+    let mut tokens = Vec::new();
+    let chars: Vec<char> = text.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let kind = match c {
+            ' ' => { i+=1; continue; },
+            '+' => TokenKind::Operator(OpType::Plus),
+            '-' => TokenKind::Operator(OpType::Minus),
+            '*' | 'x' | 'X' => TokenKind::Operator(OpType::Multiply),
+            '/' => TokenKind::Operator(OpType::Divide),
+            '(' => TokenKind::OpenParen,
+            ')' => TokenKind::CloseParen,
+            '0'..='9' => {
+                let start = i;
+                while i+1 < chars.len() && chars[i+1].is_ascii_digit() { i+=1; }
+                let digits: String = chars[start..=i].iter().collect();
+                match digits.parse::<i32>() {
+                    Ok(n) => {
+                        tokens.push(Token { kind: TokenKind::Number(n), position: start });
+                        i+=1;
+                        continue;
+                    },
+                    Err(_) => { return Err(ExpressionError::new("Number is too large", Some(start))); }
+                }
+            },
+            _ => { return Err(ExpressionError::new(&format!("Unexpected character '{}'", c), Some(i))); }
+        };
+        tokens.push(Token { kind, position: i });
+        i+=1;
+    }
+    return Ok(tokens);
+}
+
+/// Recursive descent over the grammar
+///     sum     := product (('+' | '-') product)*
+///     product := atom (('x' | '/') atom)*
+///     atom    := number | '(' sum ')'
+struct Parser {
+    tokens: Vec<Token>,
+    index: usize,
+    end: usize
+}
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index)
+    }
+    fn parse_sum(&mut self) -> Result<ExpressionNode, ExpressionError> {
+        let mut node = self.parse_product()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token { kind: TokenKind::Operator(op), .. }) if *op == OpType::Plus || *op == OpType::Minus => op.clone(),
+                _ => { return Ok(node); }
+            };
+            self.index+=1;
+            let right = self.parse_product()?;
+            node = ExpressionNode::Operation(op, Box::new(node), Box::new(right));
+        }
+    }
+    fn parse_product(&mut self) -> Result<ExpressionNode, ExpressionError> {
+        let mut node = self.parse_atom()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token { kind: TokenKind::Operator(op), .. }) if *op == OpType::Multiply || *op == OpType::Divide => op.clone(),
+                _ => { return Ok(node); }
+            };
+            self.index+=1;
+            let right = self.parse_atom()?;
+            node = ExpressionNode::Operation(op, Box::new(node), Box::new(right));
+        }
+    }
+    fn parse_atom(&mut self) -> Result<ExpressionNode, ExpressionError> {
+        let (kind, position) = match self.peek() {
+            Some(token) => (token.kind.clone(), token.position),
+            None => { return Err(ExpressionError::new("Unexpected end of expression", Some(self.end))); }
+        };
+        self.index+=1;
+        match kind {
+            TokenKind::Number(n) => Ok(ExpressionNode::Number(n)),
+            TokenKind::OpenParen => {
+                let node = self.parse_sum()?;
+                match self.peek() {
+                    Some(Token { kind: TokenKind::CloseParen, .. }) => {
+                        self.index+=1;
+                        Ok(node)
+                    },
+                    Some(token) => Err(ExpressionError::new(&format!("Expected ')' but found '{}'", token.kind), Some(token.position))),
+                    None => Err(ExpressionError::new("Missing ')'", Some(self.end)))
+                }
+            },
+            _ => Err(ExpressionError::new(&format!("Unexpected '{}'", kind), Some(position)))
+        }
+    }
+}
+/* #endregion */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluate(text: &str) -> Result<Fraction, ExpressionError> {
+        parse_expression(text).and_then(|node| node.evaluate())
+    }
+
+    #[test]
+    fn products_bind_tighter_than_sums() {
+        assert_eq!(evaluate("2 + 3 x 4"), Ok(Fraction::from(14)));
+        assert_eq!(evaluate("2 * 3 - 4 / 2"), Ok(Fraction::from(4)));
+    }
+
+    #[test]
+    fn operators_associate_left() {
+        assert_eq!(evaluate("8 / 2 / 2"), Ok(Fraction::from(2)));
+        assert_eq!(evaluate("10 - 3 - 2"), Ok(Fraction::from(5)));
+        assert_eq!(evaluate("1 / 2 / 4"), Ok(Fraction::new(1u64, 8u64)));
+    }
+
+    #[test]
+    fn parentheses_override_precedence() {
+        assert_eq!(evaluate("(2 + 3) x 4"), Ok(Fraction::from(20)));
+        assert_eq!(evaluate("8 / (2 / 2)"), Ok(Fraction::from(8)));
+        assert_eq!(evaluate("((9+1))*(8-4)"), Ok(Fraction::from(40)));
+    }
+
+    #[test]
+    fn every_input_is_used_exactly_once() {
+        let inputs = vec!(1, 2, 3, 4);
+        assert_eq!(evaluate_with_inputs("(1 + 2 + 3) x 4", &inputs), Ok(Fraction::from(24)));
+        assert!(evaluate_with_inputs("1 + 2 + 3", &inputs).is_err()); //4 left out
+        assert!(evaluate_with_inputs("1 + 1 + 2 + 3 + 4", &inputs).is_err()); //1 used twice
+        assert!(evaluate_with_inputs("1 + 2 + 3 + 5", &inputs).is_err()); //Not on the board
+        assert_eq!(evaluate_with_inputs("2 x 2 + 1", &vec!(2, 1, 2)), Ok(Fraction::from(5))); //Repeated board numbers can each be used
+    }
+
+    #[test]
+    fn trailing_tokens_are_rejected() {
+        assert_eq!(parse_expression("1 + 2 3"), Err(ExpressionError::new("Unexpected '3'", Some(6))));
+        assert_eq!(parse_expression("(1 + 2))"), Err(ExpressionError::new("Unexpected ')'", Some(7))));
+        assert_eq!(parse_expression("1 + 2 ?"), Err(ExpressionError::new("Unexpected character '?'", Some(6))));
+    }
+
+    #[test]
+    fn empty_and_unfinished_input_is_rejected() {
+        assert_eq!(parse_expression(""), Err(ExpressionError::new("Unexpected end of expression", Some(0))));
+        assert_eq!(parse_expression("   "), Err(ExpressionError::new("Unexpected end of expression", Some(3))));
+        assert_eq!(parse_expression("1 +"), Err(ExpressionError::new("Unexpected end of expression", Some(3))));
+        assert_eq!(parse_expression("()"), Err(ExpressionError::new("Unexpected ')'", Some(1))));
+    }
+
+    #[test]
+    fn unmatched_parentheses_are_rejected() {
+        assert_eq!(parse_expression("(1 + 2"), Err(ExpressionError::new("Missing ')'", Some(6))));
+        assert_eq!(parse_expression("(1 + 2 3)"), Err(ExpressionError::new("Expected ')' but found '3'", Some(7))));
+        assert_eq!(parse_expression("1 + 2)"), Err(ExpressionError::new("Unexpected ')'", Some(5))));
+    }
+
+    #[test]
+    fn dividing_by_zero_is_an_error() {
+        assert_eq!(evaluate("4 / (2 - 2)"), Err(ExpressionError::new("Division by zero", None)));
+        assert_eq!(evaluate("0 / 4"), Ok(Fraction::from(0)));
+    }
+}
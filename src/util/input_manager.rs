@@ -1,6 +1,21 @@
-use ggez::input;
+use crate::util::bindings::{Bindings, REBINDABLE_SEMANTICS};
+use ggez::{input::{self, keyboard::{KeyCode, KeyMods}}, event::{Axis, Button, winit_event::TouchPhase}, mint::Point2};
 use log::warn;
-#[derive(Eq, Hash, PartialEq, Debug)]
+use std::collections::{HashMap, HashSet};
+
+//A stick counts as pressed in a direction past the press threshold, and stays pressed until it falls back under the release one
+const AXIS_PRESS_THRESHOLD: f32 = 0.5;
+const AXIS_RELEASE_THRESHOLD: f32 = 0.3;
+
+//Held semantics that pulse `is_repeated`: menu navigation and undo. Deleting typed text repeats at the OS's own rate instead
+pub const DEFAULT_KEY_REPEATS: &'static [(InputSemantic, KeyRepeat)] = &[
+    (InputSemantic::Up, KeyRepeat { initial_delay: 0.4, interval: 0.08 }),
+    (InputSemantic::Down, KeyRepeat { initial_delay: 0.4, interval: 0.08 }),
+    (InputSemantic::Left, KeyRepeat { initial_delay: 0.4, interval: 0.08 }),
+    (InputSemantic::Right, KeyRepeat { initial_delay: 0.4, interval: 0.08 }),
+    (InputSemantic::Back, KeyRepeat { initial_delay: 0.5, interval: 0.12 })
+];
+#[derive(Clone, Copy, Eq, Hash, PartialEq, Debug)]
 pub enum InputSemantic {
     Up,
     Down,
@@ -16,64 +31,192 @@ pub enum InputSemantic {
     Hotbar2,
     Hotbar3,
     Hotbar4,
+    ToggleExpressionEntry,
+    Hint,
+    Skip,
+    Pause,
+    Pointer, //Primary pointer button; where it was pressed comes from get_cursor_position
 }
 
-#[derive(Eq, PartialEq, Clone, Copy)]
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
 pub enum InputType {
-    Keyboard(input::keyboard::KeyCode),
-    Mouse(input::mouse::MouseButton)
+    Keyboard(KeyChord),
+    Mouse(input::mouse::MouseButton),
+    Gamepad(GamepadInput),
+    Touch //A finger on the touchscreen; where comes from get_cursor_position, like the mouse
+}
+
+/// A key plus whatever has to be down already when it is pressed: modifiers, and optionally one other key.
+/// As an event, `mods` are the modifiers that were active and `held` is unused.
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub struct KeyChord {
+    pub key: KeyCode,
+    pub mods: KeyMods,
+    pub held: Option<KeyCode>
+}
+impl KeyChord {
+    pub fn new(key: KeyCode) -> KeyChord {
+        KeyChord { key, mods: KeyMods::NONE, held: None }
+    }
+    /// Builds the event for a key stroke. A modifier key doesn't count as its own modifier,
+    /// so pressing Shift on its own is plain `LShift` rather than Shift+LShift.
+    pub fn from_key_event(key: KeyCode, mods: KeyMods) -> KeyChord {
+        KeyChord::new(key).with_mods(mods.difference(get_modifier_of_key(key)))
+    }
+    pub fn with_mods(mut self, mods: KeyMods) -> KeyChord {
+        self.mods = mods;
+        self
+    }
+    pub fn with_held(mut self, held: KeyCode) -> KeyChord {
+        self.held = Some(held);
+        self
+    }
+    /// How many requirements the chord adds to its key. When one press matches several chords, only the
+    /// most specific ones trigger, so Shift+Key8 and a bare Key8 can mean different things.
+    pub fn get_specificity(&self) -> u32 {
+        self.mods.bits().count_ones()+(self.held.is_some() as u32)
+    }
+    /// Whether pressing `event` while `held_keys` are down triggers this chord. Extra modifiers are allowed;
+    /// the specificity check is what stops a bare key from firing alongside its Shift chord.
+    pub fn matches(&self, event: &KeyChord, held_keys: &HashSet<KeyCode>) -> bool {
+        self.key == event.key &&
+            event.mods.contains(self.mods) &&
+            self.held.is_none_or(|held| held_keys.contains(&held))
+    }
+}
+pub fn is_modifier_key(key: KeyCode) -> bool {
+    !get_modifier_of_key(key).is_empty()
+}
+fn get_modifier_of_key(key: KeyCode) -> KeyMods {
+    match key {
+        KeyCode::LShift | KeyCode::RShift => KeyMods::SHIFT,
+        KeyCode::LControl | KeyCode::RControl => KeyMods::CTRL,
+        KeyCode::LAlt | KeyCode::RAlt => KeyMods::ALT,
+        KeyCode::LWin | KeyCode::RWin => KeyMods::LOGO,
+        _ => KeyMods::NONE
+    }
+}
+
+/// A gamepad button, or an analog axis pushed far enough one way to act like one.
+/// Stick Y axes are positive upward.
+#[derive(Eq, PartialEq, Clone, Copy, Debug, Hash)]
+pub enum GamepadInput {
+    Button(Button),
+    AxisPositive(Axis),
+    AxisNegative(Axis)
 }
 
 pub trait InputProcessor {
-    fn process_input_pressed(&mut self);
-    fn process_input_released(&mut self);
+    /// `input` is one this processor matched; processors track each physical input separately.
+    fn process_input_pressed(&mut self, input: InputType);
+    fn process_input_released(&mut self, input: InputType);
     fn process_input(&mut self);
     fn get_input_state(&self) -> InputState;
+    /// Whether any of its inputs went down since the last process_input, even if already released again.
+    fn was_pressed_this_frame(&self) -> bool;
+    /// Whether releasing `input` should release this processor.
     fn has_input(&self, input: InputType) -> bool;
+    /// How specifically this processor matches a press of `input`, or None if it doesn't.
+    fn get_match_specificity(&self, input: InputType, _held_keys: &HashSet<KeyCode>) -> Option<u32> {
+        if self.has_input(input) { Some(0) } else { None }
+    }
 }
 
 pub struct InputStateContainer {
     state: InputState,
+    release_pending: bool, //Released on the same frame it was pressed; the release shows next frame
+    pressed_this_frame: bool
 }
 impl InputStateContainer {
+    fn new() -> InputStateContainer {
+        InputStateContainer { state: InputState::AtRest, release_pending: false, pressed_this_frame: false }
+    }
     fn process_input_pressed(&mut self) {
         self.state = InputState::Pressed;
+        self.release_pending = false;
+        self.pressed_this_frame = true;
     }
     fn process_input_released(&mut self) {
-        self.state = InputState::Released;
+        match self.state {
+            //A tap shorter than a frame still reads as Pressed for the frame it happened in
+            InputState::Pressed => { self.release_pending = true; },
+            InputState::Held => { self.state = InputState::Released; },
+            //Releasing before a press (e.g. a key that was already down when the bindings changed) changes nothing
+            _ => {}
+        }
     }
     fn process_input(&mut self) {
         match self.state {
-            InputState::Pressed => {self.state = InputState::Held},
+            InputState::Pressed => {
+                self.state = if self.release_pending { InputState::Released } else { InputState::Held };
+            },
             InputState::Released => {self.state = InputState::AtRest},
             _ => {}
         }
+        self.release_pending = false;
+        self.pressed_this_frame = false;
     }
     fn get_input_state(&self) -> InputState {
         self.state
     }
 }
 
+/// One state per physical input bound to a processor, so releasing one of two held keys leaves the other held.
+/// The processor reports the highest-priority state among them. A second input pressed while another is held
+/// therefore reads as Held rather than Pressed, since the semantic never went up; `was_pressed_this_frame` still
+/// reports the new press, so one-shot actions fire for it.
+pub struct PhysicalInputStates<K: Eq + std::hash::Hash> {
+    states: HashMap<K, InputStateContainer>
+}
+impl<K: Eq + std::hash::Hash> PhysicalInputStates<K> {
+    pub fn new() -> PhysicalInputStates<K> {
+        PhysicalInputStates { states: HashMap::new() }
+    }
+    fn process_input_pressed(&mut self, input: K) {
+        self.states.entry(input).or_insert(InputStateContainer::new()).process_input_pressed();
+    }
+    fn process_input_released(&mut self, input: K) {
+        if let Some(container) = self.states.get_mut(&input) {
+            container.process_input_released();
+        }
+    }
+    fn process_input(&mut self) {
+        for container in self.states.values_mut() {
+            container.process_input();
+        }
+        self.states.retain(|_, container| container.get_input_state() != InputState::AtRest);
+    }
+    fn get_input_state(&self) -> InputState {
+        let states: Vec<InputState> = self.states.values().map(|c| c.get_input_state()).collect();
+        get_highest_priority_input(&states)
+    }
+    fn was_pressed_this_frame(&self) -> bool {
+        self.states.values().any(|c| c.pressed_this_frame)
+    }
+}
+
 pub struct KeyboardInputProcessor {
-    state: InputStateContainer,
-    input_list: Vec<ggez::input::keyboard::KeyCode>
+    state: PhysicalInputStates<KeyCode>, //By the key pressed; a chord's modifiers and held key aren't tracked
+    input_list: Vec<KeyChord>
 }
 impl KeyboardInputProcessor {
-    pub fn new(input_list: Vec<ggez::input::keyboard::KeyCode>) -> KeyboardInputProcessor {
+    pub fn new(input_list: Vec<KeyChord>) -> KeyboardInputProcessor {
         KeyboardInputProcessor {
-            state: InputStateContainer {
-                state: InputState::AtRest,
-            },
-            input_list: input_list,
+            state: PhysicalInputStates::new(),
+            input_list,
         }
     }
 }
 impl InputProcessor for KeyboardInputProcessor {
-    fn process_input_pressed(&mut self) {
-        self.state.process_input_pressed()
+    fn process_input_pressed(&mut self, input: InputType) {
+        if let InputType::Keyboard(event) = input {
+            self.state.process_input_pressed(event.key)
+        }
     }
-    fn process_input_released(&mut self) {
-        self.state.process_input_released()
+    fn process_input_released(&mut self, input: InputType) {
+        if let InputType::Keyboard(event) = input {
+            self.state.process_input_released(event.key)
+        }
     }
     fn process_input(&mut self) {
         self.state.process_input()
@@ -81,36 +224,52 @@ impl InputProcessor for KeyboardInputProcessor {
     fn get_input_state(&self) -> InputState {
         self.state.get_input_state()
     }
+    fn was_pressed_this_frame(&self) -> bool {
+        self.state.was_pressed_this_frame()
+    }
     fn has_input(&self, input: InputType) -> bool {
         for i in self.input_list.as_slice() {
-            if InputType::Keyboard(*i) == input {
-                return true;
+            if let InputType::Keyboard(event) = input {
+                if i.key == event.key {
+                    return true;
+                }
             }
         }
         false
     }
+    fn get_match_specificity(&self, input: InputType, held_keys: &HashSet<KeyCode>) -> Option<u32> {
+        match input {
+            InputType::Keyboard(event) => self.input_list.iter()
+                .filter(|chord| chord.matches(&event, held_keys))
+                .map(|chord| chord.get_specificity())
+                .max(),
+            _ => None
+        }
+    }
 }
 
 pub struct MouseInputProcessor {
-    state: InputStateContainer,
+    state: PhysicalInputStates<ggez::input::mouse::MouseButton>,
     input_list: Vec<ggez::input::mouse::MouseButton>
 }
 impl MouseInputProcessor {
     pub fn new(input_list: Vec<ggez::input::mouse::MouseButton>) -> MouseInputProcessor {
         MouseInputProcessor {
-            state: InputStateContainer {
-                state: InputState::AtRest,
-            },
-            input_list: input_list
+            state: PhysicalInputStates::new(),
+            input_list
         }
     }
 }
 impl InputProcessor for MouseInputProcessor {
-    fn process_input_pressed(&mut self) {
-        self.state.process_input_pressed()
+    fn process_input_pressed(&mut self, input: InputType) {
+        if let InputType::Mouse(button) = input {
+            self.state.process_input_pressed(button)
+        }
     }
-    fn process_input_released(&mut self) {
-        self.state.process_input_released()
+    fn process_input_released(&mut self, input: InputType) {
+        if let InputType::Mouse(button) = input {
+            self.state.process_input_released(button)
+        }
     }
     fn process_input(&mut self) {
         self.state.process_input()
@@ -118,6 +277,9 @@ impl InputProcessor for MouseInputProcessor {
     fn get_input_state(&self) -> InputState {
         self.state.get_input_state()
     }
+    fn was_pressed_this_frame(&self) -> bool {
+        self.state.was_pressed_this_frame()
+    }
     fn has_input(&self, input: InputType) -> bool {
         for i in self.input_list.as_slice() {
             if InputType::Mouse(*i) == input {
@@ -128,7 +290,101 @@ impl InputProcessor for MouseInputProcessor {
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct GamepadInputProcessor {
+    state: PhysicalInputStates<GamepadInput>,
+    input_list: Vec<GamepadInput>
+}
+impl GamepadInputProcessor {
+    pub fn new(input_list: Vec<GamepadInput>) -> GamepadInputProcessor {
+        GamepadInputProcessor {
+            state: PhysicalInputStates::new(),
+            input_list
+        }
+    }
+}
+impl InputProcessor for GamepadInputProcessor {
+    fn process_input_pressed(&mut self, input: InputType) {
+        if let InputType::Gamepad(pad_input) = input {
+            self.state.process_input_pressed(pad_input)
+        }
+    }
+    fn process_input_released(&mut self, input: InputType) {
+        if let InputType::Gamepad(pad_input) = input {
+            self.state.process_input_released(pad_input)
+        }
+    }
+    fn process_input(&mut self) {
+        self.state.process_input()
+    }
+    fn get_input_state(&self) -> InputState {
+        self.state.get_input_state()
+    }
+    fn was_pressed_this_frame(&self) -> bool {
+        self.state.was_pressed_this_frame()
+    }
+    fn has_input(&self, input: InputType) -> bool {
+        for i in self.input_list.as_slice() {
+            if InputType::Gamepad(*i) == input {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+/// Drives its semantic from the touchscreen, if bound to it. There's only ever one touch, so no per-input states.
+pub struct TouchInputProcessor {
+    state: InputStateContainer,
+    bound: bool
+}
+impl TouchInputProcessor {
+    pub fn new(bound: bool) -> TouchInputProcessor {
+        TouchInputProcessor { state: InputStateContainer::new(), bound }
+    }
+}
+impl InputProcessor for TouchInputProcessor {
+    fn process_input_pressed(&mut self, _input: InputType) {
+        self.state.process_input_pressed()
+    }
+    fn process_input_released(&mut self, _input: InputType) {
+        self.state.process_input_released()
+    }
+    fn process_input(&mut self) {
+        self.state.process_input()
+    }
+    fn get_input_state(&self) -> InputState {
+        self.state.get_input_state()
+    }
+    fn was_pressed_this_frame(&self) -> bool {
+        self.state.pressed_this_frame
+    }
+    fn has_input(&self, input: InputType) -> bool {
+        self.bound && input == InputType::Touch
+    }
+}
+
+/// Seconds a semantic has to be held before it starts repeating, and between repeats after that.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct KeyRepeat {
+    pub initial_delay: f32,
+    pub interval: f32
+}
+
+struct RepeatTimer {
+    repeat: KeyRepeat,
+    held_for: f32,
+    next_pulse: f32, //held_for value the next pulse fires at
+    pulsed: bool //A pulse fired during the latest update
+}
+
+/// A raw press or release, in the order it arrived.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum InputEvent {
+    Pressed(InputType),
+    Released(InputType)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
 pub enum InputState {
     AtRest,
     Released,
@@ -146,16 +402,64 @@ pub fn get_highest_priority_input(list_of_inputs: &Vec<InputState>) -> InputStat
     return previous_best;
 }
 
-pub struct InputManager {
-    input_map: std::collections::HashMap<InputSemantic, Vec<usize>>,
+/// Named sets of mappings that screens push and pop, so the same key can mean different things in different places.
+#[derive(Clone, Copy, Eq, Hash, PartialEq, Debug)]
+pub enum InputContextKind {
+    Gameplay,
+    Menu,
+    TextEntry,
+    Rebinding
+}
+
+//What each built-in context keeps from the bindings. Text entry and rebinding leave the rest of the keyboard to the text or the capture.
+const MENU_SEMANTICS: &'static [InputSemantic] = &[
+    InputSemantic::Up, InputSemantic::Down, InputSemantic::Left, InputSemantic::Right,
+    InputSemantic::Accept, InputSemantic::Back, InputSemantic::Pointer, InputSemantic::Pause
+];
+const TEXT_ENTRY_SEMANTICS: &'static [InputSemantic] = &[
+    InputSemantic::Accept, InputSemantic::ToggleExpressionEntry, InputSemantic::Pause
+];
+//Any bound key might be one the player wants to type (Space is bound to Accept by default), so text entry uses these keys instead
+const TEXT_ENTRY_KEYS: &'static [(InputSemantic, KeyCode)] = &[
+    (InputSemantic::Accept, KeyCode::Return),
+    (InputSemantic::Accept, KeyCode::NumpadEnter),
+    (InputSemantic::ToggleExpressionEntry, KeyCode::Tab),
+    (InputSemantic::Pause, KeyCode::Escape)
+];
+
+/// One layer of mappings: semantics, the processors that drive them, and whether inputs it doesn't map
+/// fall through to the layers beneath it.
+pub struct InputContext {
+    input_map: HashMap<InputSemantic, Vec<usize>>,
     input_processors: Vec<Box<dyn InputProcessor>>,
+    blocks_lower: bool
 }
-impl InputManager {
-    pub fn new() -> InputManager {
-        InputManager { 
-            input_map: std::collections::HashMap::new(), 
-            input_processors: Vec::new(),
+impl InputContext {
+    pub fn new(blocks_lower: bool) -> InputContext {
+        InputContext { input_map: HashMap::new(), input_processors: Vec::new(), blocks_lower }
+    }
+    /// One keyboard, mouse, gamepad and touch processor per listed semantic, so each is mapped even when it has no inputs.
+    pub fn from_bindings(bindings: &Bindings, semantics: &[InputSemantic], blocks_lower: bool) -> InputContext {
+        let mut context = InputContext::new(blocks_lower);
+        for semantic in semantics {
+            let mut keys = Vec::new();
+            let mut buttons = Vec::new();
+            let mut pad_inputs = Vec::new();
+            for input in bindings.get_inputs(*semantic) {
+                match input {
+                    InputType::Keyboard(key) => { keys.push(*key); },
+                    InputType::Mouse(button) => { buttons.push(*button); },
+                    InputType::Gamepad(pad_input) => { pad_inputs.push(*pad_input); },
+                    InputType::Touch => {} //Not rebindable; see below
+                }
+            }
+            context.register_input(*semantic, Box::new(KeyboardInputProcessor::new(keys)));
+            context.register_input(*semantic, Box::new(MouseInputProcessor::new(buttons)));
+            context.register_input(*semantic, Box::new(GamepadInputProcessor::new(pad_inputs)));
+            //Touch is always the pointer, so bindings files written before it existed still work on touchscreens
+            context.register_input(*semantic, Box::new(TouchInputProcessor::new(*semantic == InputSemantic::Pointer)));
         }
+        context
     }
     pub fn register_input(&mut self, semantic: InputSemantic, input_processor: Box<dyn InputProcessor>) {
         self.input_processors.push(input_processor);
@@ -164,45 +468,542 @@ impl InputManager {
             None => { self.input_map.insert(semantic, vec!(self.input_processors.len()-1)); }
         }
     }
+}
+
+pub struct InputManager {
+    base: InputContext, //Beneath every pushed context; the only layer when none are pushed
+    contexts: HashMap<InputContextKind, InputContext>,
+    context_stack: Vec<InputContextKind>, //Pushed contexts, top last
+    cursor_position: Point2<f32>, //Window coordinates
+    bindings: Option<Bindings>, //What the manager was built from, if it was built from bindings
+    events: Vec<InputEvent>, //Everything since the last process_input, mapped or not
+    semantic_presses: Vec<InputSemantic>, //One entry per press of a mapped input, in arrival order
+    axis_directions: HashMap<Axis, GamepadInput>, //Axes currently pushed past the threshold, and which way
+    held_keys: HashSet<KeyCode>, //For chords that need another key down
+    repeat_timers: HashMap<InputSemantic, RepeatTimer>,
+    touches_down: u32, //Fingers on the screen; ggez doesn't say which finger an event is for
+    touch_frozen: bool //A second finger landed during the current touch
+}
+impl InputManager {
+    pub fn new() -> InputManager {
+        InputManager { 
+            base: InputContext::new(false),
+            contexts: HashMap::new(),
+            context_stack: Vec::new(),
+            cursor_position: Point2 { x: 0.0, y: 0.0 },
+            bindings: None,
+            events: Vec::new(),
+            semantic_presses: Vec::new(),
+            axis_directions: HashMap::new(),
+            held_keys: HashSet::new(),
+            repeat_timers: HashMap::new(),
+            touches_down: 0,
+            touch_frozen: false
+        }
+    }
+    /// Every semantic goes in the base layer, and each built-in context gets its share of the bindings.
+    /// All of the built-in contexts block the layers beneath them.
+    pub fn from_bindings(bindings: &Bindings) -> InputManager {
+        let mut manager = InputManager::new();
+        manager.base = InputContext::from_bindings(bindings, REBINDABLE_SEMANTICS, false);
+        manager.set_context(InputContextKind::Gameplay, InputContext::from_bindings(bindings, REBINDABLE_SEMANTICS, true));
+        manager.set_context(InputContextKind::Menu, InputContext::from_bindings(bindings, MENU_SEMANTICS, true));
+        let mut text_bindings = bindings.clone();
+        for semantic in TEXT_ENTRY_SEMANTICS {
+            let mut inputs: Vec<InputType> = bindings.get_inputs(*semantic).iter()
+                .copied()
+                .filter(|i| !matches!(i, InputType::Keyboard(_)))
+                .collect();
+            inputs.extend(TEXT_ENTRY_KEYS.iter().filter(|(s, _)| s == semantic).map(|(_, k)| InputType::Keyboard(KeyChord::new(*k))));
+            text_bindings.set(*semantic, inputs);
+        }
+        manager.set_context(InputContextKind::TextEntry, InputContext::from_bindings(&text_bindings, TEXT_ENTRY_SEMANTICS, true));
+        manager.set_context(InputContextKind::Rebinding, InputContext::new(true)); //The capture reads raw events instead
+        for (semantic, repeat) in DEFAULT_KEY_REPEATS {
+            manager.set_repeat(*semantic, Some(*repeat));
+        }
+        manager.bindings = Some(bindings.clone());
+        manager
+    }
+    /// Inputs added with register_input afterwards are not reflected here.
+    pub fn get_bindings(&self) -> Option<&Bindings> {
+        self.bindings.as_ref()
+    }
+    /// Defines (or replaces) the mappings used while `kind` is pushed.
+    pub fn set_context(&mut self, kind: InputContextKind, context: InputContext) {
+        self.contexts.insert(kind, context);
+    }
+    /// Presses that arrive from now on are looked up in `kind` first. Pushing a kind that was never defined
+    /// has no effect on lookups until it is.
+    pub fn push_context(&mut self, kind: InputContextKind) {
+        self.context_stack.push(kind);
+    }
+    pub fn pop_context(&mut self) -> Option<InputContextKind> {
+        self.context_stack.pop()
+    }
+    /// Pops and pushes just enough that the pushed contexts are exactly `contexts`, bottom first.
+    /// Contexts that are already in place stay put, along with whatever their inputs are doing.
+    pub fn sync_contexts(&mut self, contexts: &[InputContextKind]) {
+        let shared = self.context_stack.iter().zip(contexts).take_while(|(a, b)| a == b).count();
+        while self.context_stack.len() > shared {
+            self.pop_context();
+        }
+        for kind in &contexts[shared..] {
+            self.push_context(*kind);
+        }
+    }
+    /// Every press and release since the last process_input, oldest first. Contexts don't filter these.
+    pub fn get_events(&self) -> &[InputEvent] {
+        self.events.as_slice()
+    }
+    /// Raw keys and buttons pressed this frame in the order they arrived, e.g. to capture one for rebinding.
+    pub fn get_presses_this_frame(&self) -> Vec<InputType> {
+        self.get_events().iter().filter_map(|e| match e {
+            InputEvent::Pressed(input) => Some(*input),
+            InputEvent::Released(_) => None
+        }).collect()
+    }
+    /// Semantics pressed this frame in the order the presses arrived, once per press, so two quick taps
+    /// of the same key within a frame both count.
+    pub fn get_semantic_presses(&self) -> &[InputSemantic] {
+        self.semantic_presses.as_slice()
+    }
+    /// Whether the semantic went down this frame, even if it was released again before anything could read it.
+    pub fn was_pressed_this_frame(&self, semantic: InputSemantic) -> bool {
+        match self.get_mapping_layer(semantic) {
+            Some(context) => context.input_map.get(&semantic).expect("").iter().any(|&index| {
+                context.input_processors.get(index).is_some_and(|p| p.was_pressed_this_frame())
+            }),
+            None => false
+        }
+    }
+    pub fn set_cursor_position(&mut self, x: f32, y: f32) {
+        self.cursor_position = Point2 { x, y };
+    }
+    pub fn get_cursor_position(&self) -> Point2<f32> {
+        self.cursor_position
+    }
+    /// Read from the topmost active layer that maps the semantic. A semantic hidden by a blocking context is at rest.
     pub fn get_input_state(&self, semantic: InputSemantic) -> InputState {
-        match self.input_map.get(&semantic) {
-            Some(index_vec) => {
+        match self.get_mapping_layer(semantic) {
+            Some(context) => {
                 let mut input_accumulator = Vec::new();
-                for &index in index_vec {
-                    match self.input_processors.get(index) {
+                for &index in context.input_map.get(&semantic).expect("") {
+                    match context.input_processors.get(index) {
                         Some(wrapper) => { input_accumulator.push(wrapper.get_input_state()); },
                         None => {}
                     }
                 }
-                if input_accumulator.len() > 0 {
+                if !input_accumulator.is_empty() {
                     return get_highest_priority_input(&input_accumulator);
                 }
                 warn!("No input mapped for input semantic {:?}. Returning InputState::AtRest.", semantic);
                 InputState::AtRest
             },
             None => {
-                warn!("No input mapped for input semantic {:?}. Returning InputState::AtRest.", semantic);
+                if !self.base.input_map.contains_key(&semantic) {
+                    warn!("No input mapped for input semantic {:?}. Returning InputState::AtRest.", semantic);
+                }
                 InputState::AtRest
             }
         }
     }
+    /// The press goes to the topmost active layer with a processor that matches it, and within that layer only
+    /// the processors that match it most specifically are pressed.
     pub fn process_input_pressed(&mut self, input: InputType) {
-        for i in self.input_processors.as_mut_slice() {
-            if i.has_input(input) {
-                i.process_input_pressed();
+        self.events.push(InputEvent::Pressed(input));
+        for layer in self.get_active_layers() {
+            let context = match layer {
+                Some(kind) => self.contexts.get_mut(&kind).expect(""),
+                None => &mut self.base
+            };
+            let specificities: Vec<Option<u32>> = context.input_processors.iter()
+                .map(|i| i.get_match_specificity(input, &self.held_keys))
+                .collect();
+            let best = match specificities.iter().flatten().max().copied() {
+                Some(best) => best,
+                None => { continue; }
+            };
+            let mut pressed_semantics = Vec::new();
+            for (index, (i, specificity)) in context.input_processors.iter_mut().zip(specificities).enumerate() {
+                if specificity == Some(best) {
+                    i.process_input_pressed(input);
+                    for (semantic, index_vec) in context.input_map.iter() {
+                        if index_vec.contains(&index) && !pressed_semantics.contains(semantic) {
+                            pressed_semantics.push(*semantic);
+                        }
+                    }
+                }
             }
+            self.semantic_presses.extend(pressed_semantics);
+            break;
+        }
+        if let InputType::Keyboard(event) = input {
+            self.held_keys.insert(event.key);
         }
     }
+    /// Reaches every layer, active or not, so nothing stays held after its context is popped.
     pub fn process_input_released(&mut self, input: InputType) {
-        for i in self.input_processors.as_mut_slice() {
-            if i.has_input(input) {
-                i.process_input_released();
+        self.events.push(InputEvent::Released(input));
+        if let InputType::Keyboard(event) = input {
+            self.held_keys.remove(&event.key);
+        }
+        for context in std::iter::once(&mut self.base).chain(self.contexts.values_mut()) {
+            for i in context.input_processors.as_mut_slice() {
+                if i.has_input(input) {
+                    i.process_input_released(input);
+                }
+            }
+        }
+    }
+    /// Turns analog axis movement into presses and releases of `GamepadInput::AxisPositive`/`AxisNegative`.
+    pub fn process_axis(&mut self, axis: Axis, value: f32) {
+        let current = self.axis_directions.get(&axis).copied();
+        let next = if value >= AXIS_PRESS_THRESHOLD { Some(GamepadInput::AxisPositive(axis)) }
+            else if value <= -AXIS_PRESS_THRESHOLD { Some(GamepadInput::AxisNegative(axis)) }
+            //In between: hold the current direction, as long as the stick is still on its side
+            else if (value > AXIS_RELEASE_THRESHOLD && current == Some(GamepadInput::AxisPositive(axis))) ||
+                (value < -AXIS_RELEASE_THRESHOLD && current == Some(GamepadInput::AxisNegative(axis))) { current }
+            else { None };
+        if next == current { return; }
+        if let Some(previous) = current {
+            self.axis_directions.remove(&axis);
+            self.process_input_released(InputType::Gamepad(previous));
+        }
+        if let Some(direction) = next {
+            self.axis_directions.insert(axis, direction);
+            self.process_input_pressed(InputType::Gamepad(direction));
+        }
+    }
+    /// Turns touchscreen events (window coordinates) into presses and releases of `InputType::Touch`, moving the
+    /// cursor along with the finger. Only single-finger gestures count: once a second finger lands, the cursor stays
+    /// where it was and the touch is released there when the last finger lifts.
+    pub fn process_touch(&mut self, phase: TouchPhase, x: f32, y: f32) {
+        match phase {
+            TouchPhase::Started => {
+                self.touches_down += 1;
+                if self.touches_down == 1 {
+                    self.set_cursor_position(x, y);
+                    self.process_input_pressed(InputType::Touch);
+                }
+                else {
+                    self.touch_frozen = true;
+                }
+            },
+            TouchPhase::Moved => {
+                if !self.touch_frozen { self.set_cursor_position(x, y); }
+            },
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                if self.touches_down == 0 { return; } //Began before the manager was built
+                self.touches_down -= 1;
+                if self.touches_down == 0 {
+                    if !self.touch_frozen { self.set_cursor_position(x, y); }
+                    self.touch_frozen = false;
+                    self.process_input_released(InputType::Touch);
+                }
+            }
+        }
+    }
+    /// Makes the semantic pulse `is_repeated` while held, or stops it repeating with None.
+    pub fn set_repeat(&mut self, semantic: InputSemantic, repeat: Option<KeyRepeat>) {
+        match repeat {
+            Some(repeat) => {
+                self.repeat_timers.insert(semantic, RepeatTimer { repeat, held_for: 0.0, next_pulse: repeat.initial_delay, pulsed: false });
+            },
+            None => { self.repeat_timers.remove(&semantic); }
+        }
+    }
+    /// Advances hold-to-repeat by the frame's time. Call once per update, before anything reads input.
+    pub fn update(&mut self, delta: f32) {
+        let held: Vec<InputSemantic> = self.repeat_timers.keys()
+            .copied()
+            .filter(|s| self.get_input_state(*s) == InputState::Held)
+            .collect();
+        for (semantic, timer) in self.repeat_timers.iter_mut() {
+            timer.pulsed = false;
+            if !held.contains(semantic) {
+                timer.held_for = 0.0;
+                timer.next_pulse = timer.repeat.initial_delay;
+                continue;
+            }
+            timer.held_for += delta;
+            if timer.held_for >= timer.next_pulse {
+                timer.pulsed = true; //At most once a frame, however long the frame was
+                while timer.next_pulse <= timer.held_for {
+                    timer.next_pulse += timer.repeat.interval.max(0.01);
+                }
             }
         }
     }
+    /// True on the frames a held semantic repeats. Never true on the frame it was first pressed.
+    pub fn is_repeated(&self, semantic: InputSemantic) -> bool {
+        self.repeat_timers.get(&semantic).is_some_and(|timer| timer.pulsed)
+    }
+    pub fn is_pressed_or_repeated(&self, semantic: InputSemantic) -> bool {
+        self.was_pressed_this_frame(semantic) || self.is_repeated(semantic)
+    }
+    /// Ends the frame: clears the event queue and moves every state along.
     pub fn process_input(&mut self) {
-        for i in self.input_processors.as_mut_slice() {
-            i.process_input();
+        self.events.clear();
+        self.semantic_presses.clear();
+        for context in std::iter::once(&mut self.base).chain(self.contexts.values_mut()) {
+            for i in context.input_processors.as_mut_slice() {
+                i.process_input();
+            }
         }
     }
-}
\ No newline at end of file
+    /// Layers a semantic is looked up in, top first: the pushed contexts down to the first one that blocks,
+    /// then the base layer (None) if nothing blocked it.
+    fn get_active_layers(&self) -> Vec<Option<InputContextKind>> {
+        let mut layers = Vec::new();
+        for kind in self.context_stack.iter().rev() {
+            if let Some(context) = self.contexts.get(kind) {
+                layers.push(Some(*kind));
+                if context.blocks_lower { return layers; }
+            }
+        }
+        layers.push(None);
+        return layers;
+    }
+    fn get_mapping_layer(&self, semantic: InputSemantic) -> Option<&InputContext> {
+        self.get_active_layers().into_iter()
+            .map(|layer| match layer {
+                Some(kind) => self.contexts.get(&kind).expect(""),
+                None => &self.base
+            })
+            .find(|context| context.input_map.contains_key(&semantic))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(key: KeyCode) -> InputType {
+        InputType::Keyboard(KeyChord::new(key))
+    }
+    fn gameplay_manager() -> InputManager {
+        let mut manager = InputManager::from_bindings(&Bindings::defaults());
+        manager.push_context(InputContextKind::Gameplay);
+        manager
+    }
+    fn cursor(manager: &InputManager) -> (f32, f32) {
+        let position = manager.get_cursor_position();
+        (position.x, position.y)
+    }
+
+    #[test]
+    fn gamepad_buttons_drive_their_semantics() {
+        let mut manager = gameplay_manager();
+        let south = InputType::Gamepad(GamepadInput::Button(Button::South));
+        manager.process_input_pressed(south);
+        assert_eq!(manager.get_input_state(InputSemantic::Plus), InputState::Pressed);
+        assert_eq!(manager.get_input_state(InputSemantic::Minus), InputState::AtRest);
+        manager.process_input();
+        assert_eq!(manager.get_input_state(InputSemantic::Plus), InputState::Held);
+        manager.process_input_released(south);
+        assert_eq!(manager.get_input_state(InputSemantic::Plus), InputState::Released);
+        manager.process_input();
+        assert_eq!(manager.get_input_state(InputSemantic::Plus), InputState::AtRest);
+    }
+
+    #[test]
+    fn stick_presses_past_threshold_and_holds_until_release_threshold() {
+        let mut manager = gameplay_manager();
+        manager.process_axis(Axis::LeftStickY, 0.4);
+        assert_eq!(manager.get_input_state(InputSemantic::Up), InputState::AtRest);
+        manager.process_axis(Axis::LeftStickY, 0.6);
+        assert_eq!(manager.get_input_state(InputSemantic::Up), InputState::Pressed);
+        manager.process_input();
+        manager.process_axis(Axis::LeftStickY, 0.4);
+        assert_eq!(manager.get_input_state(InputSemantic::Up), InputState::Held);
+        manager.process_axis(Axis::LeftStickY, 0.2);
+        assert_eq!(manager.get_input_state(InputSemantic::Up), InputState::Released);
+    }
+
+    #[test]
+    fn stick_swinging_past_center_releases_its_direction() {
+        let mut manager = gameplay_manager();
+        manager.process_axis(Axis::LeftStickY, 0.6);
+        manager.process_input();
+        manager.process_axis(Axis::LeftStickY, -0.4);
+        assert_eq!(manager.get_input_state(InputSemantic::Up), InputState::Released);
+        assert_eq!(manager.get_input_state(InputSemantic::Down), InputState::AtRest);
+        manager.process_input();
+        manager.process_axis(Axis::LeftStickY, -0.6);
+        assert_eq!(manager.get_input_state(InputSemantic::Up), InputState::AtRest);
+        assert_eq!(manager.get_input_state(InputSemantic::Down), InputState::Pressed);
+    }
+
+    #[test]
+    fn overlapping_presses_stay_held_until_the_last_release() {
+        let mut states = PhysicalInputStates::new();
+        states.process_input_pressed(KeyCode::Return);
+        states.process_input();
+        states.process_input_pressed(KeyCode::Space);
+        states.process_input();
+        states.process_input_released(KeyCode::Return);
+        assert_eq!(states.get_input_state(), InputState::Held);
+        states.process_input();
+        assert_eq!(states.get_input_state(), InputState::Held);
+        states.process_input_released(KeyCode::Space);
+        assert_eq!(states.get_input_state(), InputState::Released);
+        states.process_input();
+        assert_eq!(states.get_input_state(), InputState::AtRest);
+    }
+
+    #[test]
+    fn second_press_while_held_reads_held_but_counts_as_a_press() {
+        let mut states = PhysicalInputStates::new();
+        states.process_input_pressed(KeyCode::Return);
+        states.process_input();
+        assert!(!states.was_pressed_this_frame());
+        states.process_input_pressed(KeyCode::Space);
+        assert_eq!(states.get_input_state(), InputState::Held);
+        assert!(states.was_pressed_this_frame());
+        states.process_input();
+        assert!(!states.was_pressed_this_frame());
+    }
+
+    #[test]
+    fn second_key_while_held_triggers_semantic_press() {
+        let mut manager = gameplay_manager();
+        manager.process_input_pressed(key(KeyCode::Return));
+        manager.process_input();
+        manager.process_input_pressed(key(KeyCode::Space));
+        assert_eq!(manager.get_input_state(InputSemantic::Accept), InputState::Held);
+        assert!(manager.was_pressed_this_frame(InputSemantic::Accept));
+        assert_eq!(manager.get_semantic_presses(), &[InputSemantic::Accept]);
+    }
+
+    #[test]
+    fn repeats_wait_for_the_initial_delay_then_pulse_each_interval() {
+        let mut manager = gameplay_manager();
+        manager.set_repeat(InputSemantic::Up, Some(KeyRepeat { initial_delay: 0.5, interval: 0.25 }));
+        manager.process_input_pressed(key(KeyCode::W));
+        manager.update(0.25);
+        assert!(!manager.is_repeated(InputSemantic::Up)); //The press itself is not a repeat
+        manager.process_input();
+        let mut pulses = Vec::new();
+        for delta in [0.25, 0.25, 0.125, 0.125, 0.25, 1.0, 0.125, 0.125] {
+            manager.update(delta);
+            pulses.push(manager.is_repeated(InputSemantic::Up));
+            manager.process_input();
+        }
+        //Held for 0.25, 0.5, 0.625, 0.75, 1.0, then 2.0: the long frame pulses only once, and the next pulse is at 2.25
+        assert_eq!(pulses, vec!(false, true, false, true, true, true, false, true));
+    }
+
+    #[test]
+    fn releasing_resets_the_repeat() {
+        let mut manager = gameplay_manager();
+        manager.set_repeat(InputSemantic::Up, Some(KeyRepeat { initial_delay: 0.5, interval: 0.25 }));
+        manager.process_input_pressed(key(KeyCode::W));
+        manager.process_input();
+        manager.update(0.5);
+        assert!(manager.is_repeated(InputSemantic::Up));
+        manager.process_input_released(key(KeyCode::W));
+        manager.update(0.25);
+        assert!(!manager.is_repeated(InputSemantic::Up));
+        manager.process_input();
+        manager.process_input_pressed(key(KeyCode::W));
+        manager.process_input();
+        manager.update(0.25);
+        assert!(!manager.is_repeated(InputSemantic::Up)); //The delay starts over
+        manager.update(0.25);
+        assert!(manager.is_repeated(InputSemantic::Up));
+        manager.set_repeat(InputSemantic::Up, None);
+        manager.update(0.5);
+        assert!(!manager.is_repeated(InputSemantic::Up));
+    }
+
+    #[test]
+    fn taps_within_one_frame_are_still_seen() {
+        let mut manager = gameplay_manager();
+        manager.process_input_pressed(key(KeyCode::H));
+        manager.process_input_released(key(KeyCode::H));
+        assert!(manager.was_pressed_this_frame(InputSemantic::Hint));
+        assert_eq!(manager.get_semantic_presses(), &[InputSemantic::Hint]);
+        manager.process_input();
+        assert!(!manager.was_pressed_this_frame(InputSemantic::Hint));
+        assert!(manager.get_semantic_presses().is_empty());
+        assert_eq!(manager.get_input_state(InputSemantic::Hint), InputState::Released);
+        for _ in 0..2 {
+            manager.process_input_pressed(key(KeyCode::H));
+            manager.process_input_released(key(KeyCode::H));
+        }
+        assert!(manager.was_pressed_this_frame(InputSemantic::Hint));
+        assert_eq!(manager.get_semantic_presses(), &[InputSemantic::Hint, InputSemantic::Hint]); //Two taps, two presses
+        assert_eq!(manager.get_events().len(), 4);
+    }
+
+    #[test]
+    fn space_does_not_accept_while_typing() {
+        let mut manager = gameplay_manager();
+        manager.push_context(InputContextKind::TextEntry);
+        manager.process_input_pressed(key(KeyCode::Space));
+        assert!(!manager.was_pressed_this_frame(InputSemantic::Accept));
+        assert_eq!(manager.get_input_state(InputSemantic::Accept), InputState::AtRest);
+        manager.process_input_pressed(key(KeyCode::Return));
+        assert!(manager.was_pressed_this_frame(InputSemantic::Accept));
+    }
+
+    #[test]
+    fn space_accepts_once_typing_stops() {
+        let mut manager = gameplay_manager();
+        manager.process_input_pressed(key(KeyCode::Space));
+        assert!(manager.was_pressed_this_frame(InputSemantic::Accept));
+    }
+
+    #[test]
+    fn single_finger_drives_the_pointer() {
+        let mut manager = gameplay_manager();
+        manager.process_touch(TouchPhase::Started, 10.0, 20.0);
+        assert_eq!(manager.get_input_state(InputSemantic::Pointer), InputState::Pressed);
+        assert_eq!(cursor(&manager), (10.0, 20.0));
+        manager.process_input();
+        manager.process_touch(TouchPhase::Moved, 30.0, 40.0);
+        assert_eq!(manager.get_input_state(InputSemantic::Pointer), InputState::Held);
+        assert_eq!(cursor(&manager), (30.0, 40.0));
+        manager.process_touch(TouchPhase::Ended, 35.0, 45.0);
+        assert_eq!(manager.get_input_state(InputSemantic::Pointer), InputState::Released);
+        assert_eq!(cursor(&manager), (35.0, 45.0));
+        manager.process_input();
+        assert_eq!(manager.get_input_state(InputSemantic::Pointer), InputState::AtRest);
+    }
+
+    #[test]
+    fn second_finger_freezes_the_cursor() {
+        let mut manager = gameplay_manager();
+        manager.process_touch(TouchPhase::Started, 10.0, 20.0);
+        manager.process_input();
+        manager.process_touch(TouchPhase::Started, 200.0, 200.0);
+        manager.process_touch(TouchPhase::Moved, 210.0, 210.0);
+        assert_eq!(cursor(&manager), (10.0, 20.0));
+        manager.process_touch(TouchPhase::Ended, 210.0, 210.0);
+        assert_eq!(manager.get_input_state(InputSemantic::Pointer), InputState::Held); //One finger is still down
+        manager.process_touch(TouchPhase::Ended, 15.0, 25.0);
+        assert_eq!(manager.get_input_state(InputSemantic::Pointer), InputState::Released);
+        assert_eq!(cursor(&manager), (10.0, 20.0));
+        manager.process_input();
+        manager.process_touch(TouchPhase::Started, 50.0, 60.0); //The next gesture follows the finger again
+        manager.process_touch(TouchPhase::Moved, 55.0, 65.0);
+        assert_eq!(cursor(&manager), (55.0, 65.0));
+    }
+
+    #[test]
+    fn touch_ending_without_a_start_is_ignored() {
+        let mut manager = gameplay_manager();
+        manager.set_cursor_position(1.0, 2.0);
+        manager.process_touch(TouchPhase::Ended, 100.0, 100.0);
+        manager.process_touch(TouchPhase::Cancelled, 100.0, 100.0);
+        assert_eq!(manager.get_input_state(InputSemantic::Pointer), InputState::AtRest);
+        assert!(manager.get_events().is_empty());
+        assert_eq!(cursor(&manager), (1.0, 2.0));
+        manager.process_touch(TouchPhase::Started, 5.0, 5.0); //Still counts from zero afterwards
+        manager.process_touch(TouchPhase::Ended, 5.0, 5.0);
+        assert_eq!(manager.get_input_state(InputSemantic::Pointer), InputState::Pressed); //A tap within one frame
+        manager.process_input();
+        assert_eq!(manager.get_input_state(InputSemantic::Pointer), InputState::Released);
+    }
+}
@@ -0,0 +1,281 @@
+use crate::util::{
+    bindings::{self, Bindings},
+    input_manager::{InputEvent, InputManager, InputType},
+    text_input::{TextEvent, TextInputChannel},
+    viewport::Viewport
+};
+use ggez::mint::Point2;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::{collections::{BTreeMap, VecDeque}, fs, path::Path};
+
+/* #region Public Interface */
+/// Everything the game reads from input during a frame, in the order it arrived. The cursor, time step and
+/// window size are only recorded when they change.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RecordedEvent {
+    Pressed(InputType),
+    Released(InputType),
+    Cursor(Point2<f32>),
+    Text(TextEvent),
+    Delta(f32), //Seconds each frame covers, from this frame on
+    Resize(Viewport) //What pointer positions are hit-tested against, from this frame on
+}
+
+/// Collects a session's input frame by frame, along with what else it takes to play it back identically:
+/// the seed boards were dealt from and the bindings in use. Recording has to start with the game.
+pub struct InputRecorder {
+    seed: u64,
+    bindings: Bindings,
+    frame: u64, //Frames recorded so far
+    events: Vec<(u64, RecordedEvent)>,
+    cursor: Option<Point2<f32>>, //Last recorded
+    delta: Option<f32>, //Last recorded
+    viewport: Option<Viewport> //Last recorded
+}
+impl InputRecorder {
+    pub fn new(seed: u64, bindings: &Bindings) -> InputRecorder {
+        InputRecorder { seed, bindings: bindings.clone(), frame: 0, events: Vec::new(), cursor: None, delta: None, viewport: None }
+    }
+    /// Takes everything that arrived since the last frame. Call once per update, before anything reads input.
+    /// `viewport` is the size the game is laid out at this frame.
+    pub fn record_frame(&mut self, delta: f32, viewport: Viewport, input_manager: &InputManager, text_input: &TextInputChannel) {
+        if self.delta != Some(delta) {
+            self.delta = Some(delta);
+            self.events.push((self.frame, RecordedEvent::Delta(delta)));
+        }
+        if self.viewport != Some(viewport) {
+            self.viewport = Some(viewport);
+            self.events.push((self.frame, RecordedEvent::Resize(viewport)));
+        }
+        let cursor = input_manager.get_cursor_position();
+        if self.cursor != Some(cursor) {
+            self.cursor = Some(cursor);
+            self.events.push((self.frame, RecordedEvent::Cursor(cursor)));
+        }
+        for event in input_manager.get_events() {
+            self.events.push((self.frame, match event {
+                InputEvent::Pressed(input) => RecordedEvent::Pressed(*input),
+                InputEvent::Released(input) => RecordedEvent::Released(*input)
+            }));
+        }
+        for event in text_input.get_events() {
+            self.events.push((self.frame, RecordedEvent::Text(event.clone())));
+        }
+        self.frame += 1;
+    }
+    pub fn to_json(&self) -> Result<String, String> {
+        let file = RecordingFile {
+            seed: self.seed,
+            bindings: self.bindings.to_names(),
+            events: self.events.iter().map(|(frame, event)| RecordedLine { frame: *frame, event: get_event_name(event) }).collect()
+        };
+        serde_json::to_string_pretty(&file).map_err(|e| e.to_string())
+    }
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        if let Some(dir) = Path::new(path).parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        fs::write(path, self.to_json()?).map_err(|e| e.to_string())
+    }
+}
+
+/// Plays a recording back into an input manager and text channel one frame at a time. Build the game from
+/// `get_seed` and `get_bindings`, then call `play_frame` where the live input would have arrived, and lay the
+/// game out at `get_viewport` so pointer positions land on the same things they did.
+pub struct InputReplay {
+    seed: u64,
+    bindings: Bindings,
+    frame: u64, //Next frame to play
+    events: VecDeque<(u64, RecordedEvent)>,
+    delta: f32,
+    viewport: Option<Viewport> //None until the recording says
+}
+impl InputReplay {
+    pub fn from_json(contents: &str) -> Result<InputReplay, String> {
+        let file: RecordingFile = serde_json::from_str(contents).map_err(|e| e.to_string())?;
+        let mut events = VecDeque::new();
+        for line in file.events {
+            match get_event_from_name(&line.event) {
+                Some(event) => { events.push_back((line.frame, event)); },
+                None => { warn!("Skipping unknown recorded event {:?} on frame {}", line.event, line.frame); } //Keys without a name can't be bound, so nothing reads them
+            }
+        }
+        Ok(InputReplay { seed: file.seed, bindings: Bindings::from_names(&file.bindings), frame: 0, events, delta: 0.0, viewport: None })
+    }
+    pub fn load(path: &str) -> Result<InputReplay, String> {
+        fs::read_to_string(path).map_err(|e| e.to_string()).and_then(|contents| InputReplay::from_json(&contents))
+    }
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+    pub fn get_bindings(&self) -> &Bindings {
+        &self.bindings
+    }
+    /// The window size as of the last frame played.
+    pub fn get_viewport(&self) -> Option<Viewport> {
+        self.viewport
+    }
+    /// Feeds in everything recorded for the next frame, as if it had just arrived, and returns the frame's time step.
+    pub fn play_frame(&mut self, input_manager: &mut InputManager, text_input: &mut TextInputChannel) -> f32 {
+        while self.events.front().is_some_and(|(frame, _)| *frame <= self.frame) {
+            let (_, event) = self.events.pop_front().expect("");
+            match event {
+                RecordedEvent::Pressed(input) => { input_manager.process_input_pressed(input); },
+                RecordedEvent::Released(input) => { input_manager.process_input_released(input); },
+                RecordedEvent::Cursor(position) => { input_manager.set_cursor_position(position.x, position.y); },
+                RecordedEvent::Text(edit) => { text_input.push_event(edit); },
+                RecordedEvent::Delta(delta) => { self.delta = delta; },
+                RecordedEvent::Resize(viewport) => { self.viewport = Some(viewport); }
+            }
+        }
+        self.frame += 1;
+        self.delta
+    }
+    /// Nothing left to play; input from here on would be live.
+    pub fn is_finished(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+/* #endregion */
+
+/* #region Secret Inner-Workings */
+#[derive(Serialize, Deserialize)]
+struct RecordingFile {
+    seed: u64,
+    bindings: BTreeMap<String, Vec<String>>,
+    events: Vec<RecordedLine>
+}
+#[derive(Serialize, Deserialize)]
+struct RecordedLine {
+    frame: u64,
+    event: String
+}
+
+const TEXT_EVENT_NAMES: &'static [(&'static str, TextEvent)] = &[
+    ("Backspace", TextEvent::Backspace),
+    ("Delete", TextEvent::Delete),
+    ("CursorLeft", TextEvent::CursorLeft),
+    ("CursorRight", TextEvent::CursorRight),
+    ("Home", TextEvent::Home),
+    ("End", TextEvent::End),
+    ("Copy", TextEvent::Copy)
+];
+
+//e.g. "Press Key:Ctrl+Z", "Release Mouse:Left", "Cursor 120 340.5", "Char 97", "Edit Backspace", "Paste \"4 x 6\"", "Delta 0.016666668",
+//"Resize 960 540".
+//Typed characters are stored by code point and pasted text as a JSON string, so spaces and quotes survive.
+fn get_event_name(event: &RecordedEvent) -> String {
+    match event {
+        RecordedEvent::Pressed(input) => format!("Press {}", bindings::get_input_name(*input)),
+        RecordedEvent::Released(input) => format!("Release {}", bindings::get_input_name(*input)),
+        RecordedEvent::Cursor(position) => format!("Cursor {} {}", position.x, position.y),
+        RecordedEvent::Text(TextEvent::Character(character)) => format!("Char {}", *character as u32),
+        RecordedEvent::Text(TextEvent::Paste(text)) => format!("Paste {}", serde_json::Value::from(text.as_str())),
+        RecordedEvent::Text(edit) => format!("Edit {:?}", edit),
+        RecordedEvent::Delta(delta) => format!("Delta {}", delta),
+        RecordedEvent::Resize(viewport) => format!("Resize {} {}", viewport.get_size().0, viewport.get_size().1)
+    }
+}
+fn get_event_from_name(name: &str) -> Option<RecordedEvent> {
+    match name.split_once(' ') {
+        Some(("Press", input)) => bindings::get_input_from_name(input).map(RecordedEvent::Pressed),
+        Some(("Release", input)) => bindings::get_input_from_name(input).map(RecordedEvent::Released),
+        Some(("Cursor", position)) => {
+            let (x, y) = position.split_once(' ')?;
+            Some(RecordedEvent::Cursor(Point2 { x: x.parse().ok()?, y: y.parse().ok()? }))
+        },
+        Some(("Char", code)) => code.parse().ok().and_then(char::from_u32).map(|c| RecordedEvent::Text(TextEvent::Character(c))),
+        Some(("Paste", text)) => serde_json::from_str(text).ok().map(|t| RecordedEvent::Text(TextEvent::Paste(t))),
+        Some(("Edit", edit)) => TEXT_EVENT_NAMES.iter().find(|e| e.0 == edit).map(|e| RecordedEvent::Text(e.1.clone())),
+        Some(("Delta", delta)) => delta.parse().ok().map(RecordedEvent::Delta),
+        Some(("Resize", size)) => {
+            let (width, height) = size.split_once(' ')?;
+            Some(RecordedEvent::Resize(Viewport::new(width.parse().ok()?, height.parse().ok()?)))
+        },
+        _ => None
+    }
+}
+/* #endregion */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::input_manager::{InputSemantic, InputState, KeyChord};
+    use ggez::input::keyboard::KeyCode;
+
+    const FRAME: f32 = 1.0/60.0;
+
+    fn key(key: KeyCode) -> InputType {
+        InputType::Keyboard(KeyChord::new(key))
+    }
+    //Records each frame's presses and releases, advancing the manager between frames like the game does
+    fn record(frames: &[(&[InputType], &[InputType])]) -> InputRecorder {
+        let mut recorder = InputRecorder::new(42, &Bindings::defaults());
+        let mut manager = InputManager::from_bindings(&Bindings::defaults());
+        let mut text_input = TextInputChannel::new();
+        for (pressed, released) in frames {
+            for input in pressed.iter() { manager.process_input_pressed(*input); }
+            for input in released.iter() { manager.process_input_released(*input); }
+            recorder.record_frame(FRAME, Viewport::reference(), &manager, &text_input);
+            manager.process_input();
+            text_input.clear();
+        }
+        recorder
+    }
+
+    #[test]
+    fn recording_survives_a_round_trip() {
+        let mut recorder = InputRecorder::new(7, &Bindings::defaults());
+        let mut manager = InputManager::from_bindings(&Bindings::defaults());
+        let mut text_input = TextInputChannel::new();
+        manager.process_input_pressed(key(KeyCode::Tab));
+        manager.set_cursor_position(120.0, 340.5);
+        text_input.push_character(' ');
+        text_input.push_character('"');
+        text_input.push_event(TextEvent::Backspace);
+        text_input.push_event(TextEvent::Paste("(4 x 6) \"x\"".to_string()));
+        recorder.record_frame(FRAME, Viewport::new(1280.0, 720.0), &manager, &text_input);
+        manager.process_input();
+        text_input.clear();
+        manager.process_input_released(key(KeyCode::Tab));
+        recorder.record_frame(FRAME*2.0, Viewport::reference(), &manager, &text_input);
+
+        let replay = InputReplay::from_json(&recorder.to_json().expect("")).expect("");
+        assert_eq!(replay.get_seed(), 7);
+        assert_eq!(replay.get_bindings(), &Bindings::defaults());
+        assert_eq!(replay.events.iter().cloned().collect::<Vec<(u64, RecordedEvent)>>(), recorder.events);
+    }
+
+    #[test]
+    fn replay_reproduces_semantic_states() {
+        let recorder = record(&[(&[key(KeyCode::Return)], &[]), (&[], &[]), (&[], &[key(KeyCode::Return)]), (&[], &[])]);
+        let mut replay = InputReplay::from_json(&recorder.to_json().expect("")).expect("");
+        let mut manager = InputManager::from_bindings(replay.get_bindings());
+        let mut text_input = TextInputChannel::new();
+        let expected = [InputState::Pressed, InputState::Held, InputState::Released, InputState::AtRest];
+        for state in expected {
+            assert_eq!(replay.play_frame(&mut manager, &mut text_input), FRAME);
+            assert_eq!(manager.get_input_state(InputSemantic::Accept), state);
+            manager.process_input();
+        }
+        assert!(replay.is_finished()); //Nothing was recorded after the release
+        assert_eq!(replay.get_viewport(), Some(Viewport::reference()));
+    }
+
+    #[test]
+    fn replay_applies_recorded_window_size() {
+        let mut recorder = InputRecorder::new(1, &Bindings::defaults());
+        let manager = InputManager::from_bindings(&Bindings::defaults());
+        let text_input = TextInputChannel::new();
+        recorder.record_frame(FRAME, Viewport::reference(), &manager, &text_input);
+        recorder.record_frame(FRAME, Viewport::new(1920.0, 1080.0), &manager, &text_input);
+        let mut replay = InputReplay::from_json(&recorder.to_json().expect("")).expect("");
+        let mut manager = InputManager::from_bindings(replay.get_bindings());
+        let mut text_input = TextInputChannel::new();
+        replay.play_frame(&mut manager, &mut text_input);
+        assert_eq!(replay.get_viewport(), Some(Viewport::reference()));
+        replay.play_frame(&mut manager, &mut text_input);
+        assert_eq!(replay.get_viewport(), Some(Viewport::new(1920.0, 1080.0)));
+    }
+}
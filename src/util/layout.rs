@@ -0,0 +1,193 @@
+use ggez::mint::Point2;
+
+/* #region Public Interface */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Alignment {
+    Start, //Left or top
+    Center
+}
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Direction {
+    Horizontal,
+    Vertical
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Padding {
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub left: f32
+}
+impl Padding {
+    pub fn uniform(amount: f32) -> Padding {
+        Padding { top: amount, right: amount, bottom: amount, left: amount }
+    }
+}
+
+/// Axis-aligned rectangle, top-left corner plus size.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LayoutBox {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32
+}
+impl LayoutBox {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> LayoutBox {
+        LayoutBox { x, y, width, height }
+    }
+    /// A box of the given size whose point at (x_percent, y_percent) sits on the same point of this box,
+    /// so (0.5, 0.5) centers it and (1.0, 0.0) pins it to the top-right corner.
+    pub fn anchored_in(&self, x_percent: f32, y_percent: f32, width: f32, height: f32) -> LayoutBox {
+        LayoutBox::new(
+            self.x+(self.width-width)*x_percent,
+            self.y+(self.height-height)*y_percent,
+            width,
+            height
+        )
+    }
+    /// Shrinks the box by the padding on each side. Never goes below zero size.
+    pub fn inset(&self, padding: &Padding) -> LayoutBox {
+        LayoutBox::new(
+            self.x+padding.left,
+            self.y+padding.top,
+            (self.width-padding.left-padding.right).max(0.0),
+            (self.height-padding.top-padding.bottom).max(0.0)
+        )
+    }
+    pub fn get_top_left(&self) -> Point2<f32> {
+        Point2 { x: self.x, y: self.y }
+    }
+}
+
+/// A row or column of equally sized cells. Points are the top-left corner of each cell.
+pub struct ListLayout {
+    origin: Point2<f32>,
+    direction: Direction,
+    spacing: f32, //Size of each cell along the list
+    alignment: Alignment //Which part of the list sits on the origin
+}
+impl ListLayout {
+    /// Row centered on (x, y).
+    pub fn horizontal(x: f32, y: f32, spacing: f32) -> ListLayout {
+        ListLayout { origin: Point2 { x, y }, direction: Direction::Horizontal, spacing, alignment: Alignment::Center }
+    }
+    /// Column hanging down from (x, y).
+    pub fn vertical(x: f32, y: f32, spacing: f32) -> ListLayout {
+        ListLayout { origin: Point2 { x, y }, direction: Direction::Vertical, spacing, alignment: Alignment::Start }
+    }
+    pub fn with_alignment(mut self, alignment: Alignment) -> ListLayout {
+        self.alignment = alignment;
+        self
+    }
+    /// Always returns exactly `count` points.
+    pub fn get_points(&self, count: usize) -> Vec<Point2<f32>> {
+        let start = get_alignment_offset((count as f32)*self.spacing, self.alignment);
+        let mut points = Vec::with_capacity(count);
+        for i in 0..count {
+            let offset = start+(i as f32)*self.spacing;
+            points.push(match self.direction {
+                Direction::Horizontal => Point2 { x: self.origin.x+offset, y: self.origin.y },
+                Direction::Vertical => Point2 { x: self.origin.x, y: self.origin.y+offset }
+            });
+        }
+        return points;
+    }
+}
+
+/// Cells filled row by row, `columns` to a row. Rows run down from the origin and are aligned horizontally
+/// around it; a grid with a single row matches a horizontal `ListLayout` with the same alignment.
+pub struct GridLayout {
+    origin: Point2<f32>,
+    columns: usize,
+    cell_width: f32,
+    cell_height: f32,
+    alignment: Alignment
+}
+impl GridLayout {
+    pub fn new(x: f32, y: f32, columns: usize, cell_width: f32, cell_height: f32) -> GridLayout {
+        GridLayout { origin: Point2 { x, y }, columns: columns.max(1), cell_width, cell_height, alignment: Alignment::Center }
+    }
+    /// Always returns exactly `count` points.
+    pub fn get_points(&self, count: usize) -> Vec<Point2<f32>> {
+        let mut points = Vec::with_capacity(count);
+        let mut row = 0;
+        while row*self.columns < count {
+            let in_row = (count-row*self.columns).min(self.columns);
+            let y = self.origin.y+(row as f32)*self.cell_height;
+            let row_layout = ListLayout::horizontal(self.origin.x, y, self.cell_width).with_alignment(self.alignment);
+            points.extend(row_layout.get_points(in_row));
+            row += 1;
+        }
+        return points;
+    }
+}
+/* #endregion */
+
+/* #region Secret Inner-Workings */
+fn get_alignment_offset(extent: f32, alignment: Alignment) -> f32 {
+    match alignment {
+        Alignment::Start => 0.0,
+        Alignment::Center => -extent/2.0
+    }
+}
+/* #endregion */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_pairs(points: Vec<Point2<f32>>) -> Vec<(f32, f32)> {
+        points.into_iter().map(|p| (p.x, p.y)).collect()
+    }
+
+    #[test]
+    fn rows_center_on_their_origin() {
+        let points = ListLayout::horizontal(100.0, 50.0, 40.0).get_points(4);
+        assert_eq!(to_pairs(points), vec!((20.0, 50.0), (60.0, 50.0), (100.0, 50.0), (140.0, 50.0)));
+        let points = ListLayout::horizontal(100.0, 50.0, 40.0).with_alignment(Alignment::Start).get_points(2);
+        assert_eq!(to_pairs(points), vec!((100.0, 50.0), (140.0, 50.0)));
+    }
+
+    #[test]
+    fn columns_hang_from_their_origin() {
+        let points = ListLayout::vertical(10.0, 20.0, 24.0).get_points(3);
+        assert_eq!(to_pairs(points), vec!((10.0, 20.0), (10.0, 44.0), (10.0, 68.0)));
+        assert!(ListLayout::vertical(10.0, 20.0, 24.0).get_points(0).is_empty());
+    }
+
+    #[test]
+    fn fractional_spacing_gives_exact_counts() {
+        //Adding up 0.3 six times overshoots 6*0.3 in f32, which gave the old accumulating loop a seventh point
+        let points = ListLayout::horizontal(0.0, 0.0, 0.3).get_points(6);
+        assert_eq!(points.len(), 6);
+        for (i, p) in points.iter().enumerate() {
+            assert!((p.x-(-0.9+0.3*i as f32)).abs() < 1e-5);
+        }
+        for count in 0..64 {
+            assert_eq!(ListLayout::horizontal(0.0, 0.0, 0.7).get_points(count).len(), count);
+            assert_eq!(ListLayout::vertical(0.0, 0.0, 0.1).get_points(count).len(), count);
+            assert_eq!(GridLayout::new(0.0, 0.0, 8, 0.3, 0.3).get_points(count).len(), count);
+        }
+    }
+
+    #[test]
+    fn grids_fill_row_by_row_and_center_each_row() {
+        let points = GridLayout::new(0.0, 0.0, 3, 10.0, 20.0).get_points(7);
+        assert_eq!(to_pairs(points), vec!(
+            (-15.0, 0.0), (-5.0, 0.0), (5.0, 0.0),
+            (-15.0, 20.0), (-5.0, 20.0), (5.0, 20.0),
+            (-5.0, 40.0)
+        ));
+    }
+
+    #[test]
+    fn boxes_anchor_and_inset() {
+        let screen = LayoutBox::new(0.0, 0.0, 800.0, 600.0);
+        assert_eq!(screen.anchored_in(0.5, 0.5, 200.0, 100.0), LayoutBox::new(300.0, 250.0, 200.0, 100.0));
+        assert_eq!(screen.anchored_in(1.0, 0.0, 200.0, 100.0), LayoutBox::new(600.0, 0.0, 200.0, 100.0));
+        let panel = LayoutBox::new(10.0, 10.0, 30.0, 20.0).inset(&Padding::uniform(12.0));
+        assert_eq!(panel, LayoutBox::new(22.0, 22.0, 6.0, 0.0));
+    }
+}
@@ -0,0 +1,194 @@
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData
+};
+
+/// Typed, generational reference to an entry in a `Registry<T>`.
+/// A handle to a removed entry never resolves again, even once its slot has been reused.
+pub struct Handle<T: ?Sized> {
+    index: u32,
+    generation: u32,
+    _marker: PhantomData<fn() -> Box<T>>
+}
+impl<T: ?Sized> Handle<T> {
+    fn new(index: u32, generation: u32) -> Handle<T> {
+        Handle { index, generation, _marker: PhantomData }
+    }
+}
+//Implemented by hand so that T itself doesn't need to be Clone/Eq/etc.
+impl<T: ?Sized> Clone for Handle<T> {
+    fn clone(&self) -> Self { *self }
+}
+impl<T: ?Sized> Copy for Handle<T> {}
+impl<T: ?Sized> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index && self.generation == other.generation
+    }
+}
+impl<T: ?Sized> Eq for Handle<T> {}
+impl<T: ?Sized> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+        self.generation.hash(state);
+    }
+}
+impl<T: ?Sized> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Handle({}v{})", self.index, self.generation)
+    }
+}
+
+struct Slot<T: ?Sized> {
+    generation: u32,
+    sequence: u64, //When the current value was inserted, counting every insert; slots are reused out of order
+    value: Option<Box<T>>
+}
+
+/// Slot map of boxed values. Removal is deferred: `remove` only queues the handle, and the entry stays
+/// reachable until `flush_removals` is called (once per frame), so nothing disappears mid-iteration.
+pub struct Registry<T: ?Sized> {
+    slots: Vec<Slot<T>>,
+    free_list: Vec<u32>,
+    pending_removals: Vec<Handle<T>>,
+    next_sequence: u64
+}
+impl<T: ?Sized> Registry<T> {
+    pub fn new() -> Registry<T> {
+        Registry { slots: Vec::new(), free_list: Vec::new(), pending_removals: Vec::new(), next_sequence: 0 }
+    }
+    pub fn insert(&mut self, value: Box<T>) -> Handle<T> {
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        match self.free_list.pop() {
+            Some(index) => {
+                let slot = self.slots.get_mut(index as usize).expect("Free list pointed past the end of the registry");
+                slot.value = Some(value);
+                slot.sequence = sequence;
+                Handle::new(index, slot.generation)
+            },
+            None => {
+                self.slots.push(Slot { generation: 0, sequence, value: Some(value) });
+                Handle::new((self.slots.len()-1) as u32, 0)
+            }
+        }
+    }
+    pub fn get(&self, handle: Handle<T>) -> Option<&T> {
+        match self.slots.get(handle.index as usize) {
+            Some(slot) if slot.generation == handle.generation => slot.value.as_deref(),
+            _ => None
+        }
+    }
+    pub fn get_mut(&mut self, handle: Handle<T>) -> Option<&mut T> {
+        match self.slots.get_mut(handle.index as usize) {
+            Some(slot) if slot.generation == handle.generation => slot.value.as_deref_mut(),
+            _ => None
+        }
+    }
+    pub fn contains(&self, handle: Handle<T>) -> bool {
+        self.get(handle).is_some()
+    }
+    /// Queues the entry for removal at the next `flush_removals`. Stale or repeated handles are ignored.
+    pub fn remove(&mut self, handle: Handle<T>) {
+        if self.contains(handle) && !self.pending_removals.contains(&handle) {
+            self.pending_removals.push(handle);
+        }
+    }
+    pub fn is_removal_pending(&self, handle: Handle<T>) -> bool {
+        self.pending_removals.contains(&handle)
+    }
+    /// Insertion order: the entry with the lower sequence was inserted first.
+    pub fn get_sequence(&self, handle: Handle<T>) -> Option<u64> {
+        match self.slots.get(handle.index as usize) {
+            Some(slot) if slot.generation == handle.generation && slot.value.is_some() => Some(slot.sequence),
+            _ => None
+        }
+    }
+    pub fn flush_removals(&mut self) {
+        for handle in self.pending_removals.drain(..) {
+            if let Some(slot) = self.slots.get_mut(handle.index as usize) {
+                if slot.generation == handle.generation && slot.value.is_some() {
+                    slot.value = None;
+                    slot.generation = slot.generation.wrapping_add(1);
+                    self.free_list.push(handle.index);
+                }
+            }
+        }
+    }
+    /// Every live entry, oldest first, whichever slots they ended up in.
+    pub fn handles(&self) -> Vec<Handle<T>> {
+        let mut live = Vec::new();
+        for (i, slot) in self.slots.iter().enumerate() {
+            if slot.value.is_some() {
+                live.push((slot.sequence, Handle::new(i as u32, slot.generation)));
+            }
+        }
+        live.sort_by_key(|(sequence, _)| *sequence);
+        live.into_iter().map(|(_, handle)| handle).collect()
+    }
+    /// Visits every live entry in slot order, which is not insertion order once slots have been reused.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Box<T>> {
+        self.slots.iter_mut().filter_map(|slot| slot.value.as_mut())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_then_get() {
+        let mut registry: Registry<i32> = Registry::new();
+        let a = registry.insert(Box::new(1));
+        let b = registry.insert(Box::new(2));
+        assert_eq!(registry.get(a), Some(&1));
+        assert_eq!(registry.get(b), Some(&2));
+        *registry.get_mut(b).expect("") = 3;
+        assert_eq!(registry.get(b), Some(&3));
+    }
+
+    #[test]
+    fn removal_waits_for_flush() {
+        let mut registry: Registry<i32> = Registry::new();
+        let a = registry.insert(Box::new(1));
+        registry.remove(a);
+        registry.remove(a);
+        assert!(registry.contains(a));
+        assert!(registry.is_removal_pending(a));
+        assert_eq!(registry.handles(), vec!(a));
+        registry.flush_removals();
+        assert!(!registry.contains(a));
+        assert!(!registry.is_removal_pending(a));
+        assert!(registry.handles().is_empty());
+    }
+
+    #[test]
+    fn stale_handle_misses_reused_slot() {
+        let mut registry: Registry<i32> = Registry::new();
+        let old = registry.insert(Box::new(1));
+        registry.remove(old);
+        registry.flush_removals();
+        let new = registry.insert(Box::new(2));
+        assert_ne!(old, new);
+        assert_eq!(registry.get(old), None);
+        assert_eq!(registry.get(new), Some(&2));
+        registry.remove(old); //Must not queue the new occupant
+        registry.flush_removals();
+        assert_eq!(registry.get(new), Some(&2));
+    }
+
+    #[test]
+    fn handles_are_in_insertion_order() {
+        let mut registry: Registry<i32> = Registry::new();
+        let a = registry.insert(Box::new(1));
+        let b = registry.insert(Box::new(2));
+        let c = registry.insert(Box::new(3));
+        registry.remove(a);
+        registry.flush_removals();
+        let d = registry.insert(Box::new(4)); //Lands in a's slot, ahead of b and c
+        assert_eq!(registry.handles(), vec!(b, c, d));
+        let mut values: Vec<i32> = registry.iter_mut().map(|v| **v).collect();
+        values.sort();
+        assert_eq!(values, vec!(2, 3, 4));
+    }
+}
@@ -0,0 +1,141 @@
+use crate::util::data_generator::InputDifficulty;
+
+const TIME_BONUS_WINDOW: f32 = 60.0; //Seconds before the time bonus runs out
+const TIME_BONUS_PER_SECOND: f32 = 5.0;
+const UNDO_PENALTY: f32 = 10.0;
+const HINT_PENALTY: f32 = 75.0;
+const MINIMUM_AWARD: f32 = 10.0;
+const STREAK_MULTIPLIER_STEP: f32 = 0.1;
+const MAX_MULTIPLIER: f32 = 2.0;
+
+/// Everything that went into solving a single board.
+pub struct SolveRecord {
+    pub difficulty: InputDifficulty,
+    pub seconds: f32,
+    pub undos: u32,
+    pub hints: u32
+}
+
+pub struct ScoreKeeper {
+    score: u64,
+    streak: u32,
+    best_streak: u32,
+    boards_solved: u32,
+    boards_forfeited: u32,
+    last_award: u64
+}
+impl ScoreKeeper {
+    pub fn new() -> ScoreKeeper {
+        ScoreKeeper {
+            score: 0,
+            streak: 0,
+            best_streak: 0,
+            boards_solved: 0,
+            boards_forfeited: 0,
+            last_award: 0
+        }
+    }
+    /// Awards points for a solved board and extends the streak. Returns the points awarded.
+    pub fn record_solve(&mut self, record: &SolveRecord) -> u64 {
+        let award = (get_base_points(record) * self.get_multiplier()).round() as u64;
+        self.score += award;
+        self.last_award = award;
+        self.boards_solved += 1;
+        self.streak += 1;
+        if self.streak > self.best_streak {
+            self.best_streak = self.streak;
+        }
+        return award;
+    }
+    /// A board was given up on (skipped or timed out). Breaks the streak.
+    pub fn record_forfeit(&mut self) {
+        self.streak = 0;
+        self.last_award = 0;
+        self.boards_forfeited += 1;
+    }
+    pub fn reset(&mut self) {
+        *self = ScoreKeeper::new();
+    }
+    pub fn get_multiplier(&self) -> f32 {
+        (1.0 + STREAK_MULTIPLIER_STEP*(self.streak as f32)).min(MAX_MULTIPLIER)
+    }
+    pub fn get_score(&self) -> u64 { self.score }
+    pub fn get_streak(&self) -> u32 { self.streak }
+    pub fn get_best_streak(&self) -> u32 { self.best_streak }
+    pub fn get_boards_solved(&self) -> u32 { self.boards_solved }
+    pub fn get_boards_forfeited(&self) -> u32 { self.boards_forfeited }
+    pub fn get_last_award(&self) -> u64 { self.last_award }
+}
+
+fn get_difficulty_points(difficulty: InputDifficulty) -> f32 {
+    match difficulty {
+        InputDifficulty::Easy => 100.0,
+        InputDifficulty::Moderate => 200.0,
+        InputDifficulty::Hard => 350.0
+    }
+}
+
+fn get_base_points(record: &SolveRecord) -> f32 {
+    let time_bonus = (TIME_BONUS_WINDOW - record.seconds).max(0.0)*TIME_BONUS_PER_SECOND;
+    let penalties = (record.undos as f32)*UNDO_PENALTY + (record.hints as f32)*HINT_PENALTY;
+    (get_difficulty_points(record.difficulty) + time_bonus - penalties).max(MINIMUM_AWARD)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(difficulty: InputDifficulty, seconds: f32, undos: u32, hints: u32) -> SolveRecord {
+        SolveRecord { difficulty, seconds, undos, hints }
+    }
+
+    #[test]
+    fn quick_solves_earn_the_time_bonus() {
+        let mut score = ScoreKeeper::new();
+        assert_eq!(score.record_solve(&record(InputDifficulty::Easy, 0.0, 0, 0)), 400); //100 + 60s*5
+        score.reset();
+        assert_eq!(score.record_solve(&record(InputDifficulty::Hard, 50.0, 0, 0)), 400); //350 + 10s*5
+        score.reset();
+        assert_eq!(score.record_solve(&record(InputDifficulty::Moderate, 90.0, 0, 0)), 200); //Bonus long gone
+    }
+
+    #[test]
+    fn undos_and_hints_cost_points_down_to_the_minimum() {
+        let mut score = ScoreKeeper::new();
+        assert_eq!(score.record_solve(&record(InputDifficulty::Hard, 60.0, 2, 1)), 255); //350 - 2*10 - 75
+        score.reset();
+        assert_eq!(score.record_solve(&record(InputDifficulty::Easy, 60.0, 3, 2)), 10);
+    }
+
+    #[test]
+    fn streaks_multiply_later_awards_up_to_the_cap() {
+        let mut score = ScoreKeeper::new();
+        let slow_easy = record(InputDifficulty::Easy, 60.0, 0, 0);
+        assert_eq!(score.record_solve(&slow_easy), 100); //The first solve has no streak behind it yet
+        assert_eq!(score.record_solve(&slow_easy), 110);
+        assert_eq!(score.record_solve(&slow_easy), 120);
+        assert_eq!(score.get_score(), 330);
+        for _ in 0..20 {
+            score.record_solve(&slow_easy);
+        }
+        assert_eq!(score.get_multiplier(), MAX_MULTIPLIER);
+        assert_eq!(score.record_solve(&slow_easy), 200);
+    }
+
+    #[test]
+    fn forfeits_break_the_streak_but_keep_the_best() {
+        let mut score = ScoreKeeper::new();
+        let slow_easy = record(InputDifficulty::Easy, 60.0, 0, 0);
+        score.record_solve(&slow_easy);
+        score.record_solve(&slow_easy);
+        score.record_forfeit();
+        assert_eq!(score.get_streak(), 0);
+        assert_eq!(score.get_best_streak(), 2);
+        assert_eq!(score.get_last_award(), 0);
+        assert_eq!(score.get_boards_forfeited(), 1);
+        assert_eq!(score.get_multiplier(), 1.0);
+        assert_eq!(score.record_solve(&slow_easy), 100);
+        assert_eq!(score.get_boards_solved(), 3);
+        assert_eq!(score.get_score(), 310);
+    }
+}
@@ -0,0 +1,194 @@
+use std::collections::HashSet;
+use fraction::Fraction;
+use crate::util::{data_generator::OpType, expression_parser::{self, ExpressionNode}};
+
+/* #region Public Interface */
+#[derive(Debug, Clone)]
+pub struct SolutionStep {
+    pub left: Fraction,
+    pub op_type: OpType,
+    pub right: Fraction,
+    pub result: Fraction
+}
+impl std::fmt::Display for SolutionStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} {} {} = {}", self.left, self.op_type.symbol(), self.right, self.result)
+    }
+}
+#[derive(Debug, Clone)]
+pub struct Solution {
+    pub expression: String,
+    pub canonical: String, //See get_canonical_expression
+    pub steps: Vec<SolutionStep>
+}
+
+/// Finds every distinct way of combining all of `values` into `target`.
+/// Solutions are distinct by their canonical expression, so commuted additions and multiplications are only counted once,
+/// however deep in the expression they are.
+pub fn find_solutions(values: &Vec<Fraction>, target: Fraction) -> Vec<Solution> {
+    let mut terms = Vec::new();
+    for v in values {
+        terms.push(Term { value: *v, expression: v.to_string(), canonical: v.to_string(), steps: Vec::new() });
+    }
+    let mut seen = HashSet::new();
+    let mut solutions = Vec::new();
+    search(terms, target, &mut seen, &mut solutions, usize::MAX);
+    return solutions;
+}
+/// Like `find_solutions`, but stops searching as soon as one solution turns up.
+pub fn find_first_solution(values: &Vec<Fraction>, target: Fraction) -> Option<Solution> {
+    let mut terms = Vec::new();
+    for v in values {
+        terms.push(Term { value: *v, expression: v.to_string(), canonical: v.to_string(), steps: Vec::new() });
+    }
+    let mut seen = HashSet::new();
+    let mut solutions = Vec::new();
+    search(terms, target, &mut seen, &mut solutions, 1);
+    return solutions.pop();
+}
+pub fn find_solutions_for_input(input: &[i32], target: Fraction) -> Vec<Solution> {
+    let values: Vec<Fraction> = input.iter().map(|i| Fraction::from(*i)).collect();
+    find_solutions(&values, target)
+}
+/// Writes `expression` the way the solver tells solutions apart: fully parenthesized, with the operands of every
+/// addition and multiplication in sorted order. "4 x (1 + 9)" and "((9 + 1) * 4)" both become "(1 + 9) x 4".
+/// None if it doesn't parse.
+pub fn get_canonical_expression(expression: &str) -> Option<String> {
+    match expression_parser::parse_expression(expression) {
+        Ok(node) => Some(strip_outer_parens(&get_canonical_node(&node))),
+        Err(_) => None
+    }
+}
+/// Drops the parentheses around a fully-parenthesized expression such as "((9 + 1) x 4)".
+/// Anything else, like "(1 + 2) x (3 + 4)", comes back as it was.
+pub fn strip_outer_parens(expression: &str) -> String {
+    if !expression.starts_with('(') { return expression.to_string(); }
+    let mut depth = 0;
+    for (i, c) in expression.char_indices() {
+        match c {
+            '(' => { depth += 1; },
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    //The first paren has to close at the very end to wrap the whole thing
+                    if i == expression.len()-1 { return expression[1..i].to_string(); }
+                    break;
+                }
+            },
+            _ => {}
+        }
+    }
+    expression.to_string()
+}
+/* #endregion */
+
+/* #region Secret Inner-Workings */
+#[derive(Clone)]
+struct Term {
+    value: Fraction,
+    expression: String,
+    canonical: String,
+    steps: Vec<SolutionStep>
+}
+
+const OPERATIONS: &'static [OpType] = &[OpType::Plus, OpType::Minus, OpType::Multiply, OpType::Divide];
+
+//Returns whether `limit` solutions have been found, so the callers up the stack can stop too
+fn search(terms: Vec<Term>, target: Fraction, seen: &mut HashSet<String>, solutions: &mut Vec<Solution>, limit: usize) -> bool {
+    if terms.len() == 1 {
+        let term = terms.first().expect("");
+        if term.value == target {
+            let canonical = strip_outer_parens(&term.canonical);
+            if seen.insert(canonical.clone()) {
+                solutions.push(Solution { expression: strip_outer_parens(&term.expression), canonical, steps: term.steps.clone() });
+            }
+        }
+        return solutions.len() >= limit;
+    }
+    for i in 0..terms.len() {
+        for j in 0..terms.len() {
+            if i == j { continue; }
+            let left = terms.get(i).expect("");
+            let right = terms.get(j).expect("");
+            for op_type in OPERATIONS {
+                let commutative = *op_type == OpType::Plus || *op_type == OpType::Multiply;
+                if commutative && i > j { continue; }
+                if *op_type == OpType::Divide && right.value == Fraction::from(0) { continue; }
+                let result = op_type.apply(left.value, right.value).expect("");
+                let mut steps = left.steps.clone();
+                steps.extend(right.steps.iter().cloned());
+                steps.push(SolutionStep { left: left.value, op_type: op_type.clone(), right: right.value, result });
+                let combined = Term {
+                    value: result,
+                    expression: format!("({} {} {})", left.expression, op_type.symbol(), right.expression),
+                    canonical: get_canonical_operation(op_type, &left.canonical, &right.canonical),
+                    steps
+                };
+                let mut remaining = vec!(combined);
+                for (k, t) in terms.iter().enumerate() {
+                    if k != i && k != j { remaining.push(t.clone()); }
+                }
+                if search(remaining, target, seen, solutions, limit) { return true; }
+            }
+        }
+    }
+    return false;
+}
+fn get_canonical_node(node: &ExpressionNode) -> String {
+    match node {
+        ExpressionNode::Number(n) => n.to_string(),
+        ExpressionNode::Operation(op_type, left, right) => get_canonical_operation(op_type, &get_canonical_node(left), &get_canonical_node(right))
+    }
+}
+fn get_canonical_operation(op_type: &OpType, left: &str, right: &str) -> String {
+    let commutative = *op_type == OpType::Plus || *op_type == OpType::Multiply;
+    if commutative && right < left {
+        return format!("({} {} {})", right, op_type.symbol(), left);
+    }
+    format!("({} {} {})", left, op_type.symbol(), right)
+}
+/* #endregion */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commuted_groups_are_one_solution() {
+        let solutions = find_solutions_for_input(&[1, 2, 3, 4], Fraction::from(10));
+        let canonicals: Vec<&String> = solutions.iter().map(|sol| &sol.canonical).collect();
+        let mut deduped = canonicals.clone();
+        deduped.sort();
+        deduped.dedup();
+        assert_eq!(canonicals.len(), deduped.len());
+        let grouped = get_canonical_expression("(1 + 2) + (3 + 4)").expect("");
+        assert_eq!(Some(grouped.clone()), get_canonical_expression("(3 + 4) + (1 + 2)"));
+        assert_eq!(solutions.iter().filter(|sol| sol.canonical == grouped).count(), 1);
+    }
+
+    #[test]
+    fn typed_expressions_match_their_solution() {
+        let solutions = find_solutions_for_input(&[9, 1, 4], Fraction::from(40));
+        let typed = get_canonical_expression("4 * (1+9)").expect("");
+        assert!(solutions.iter().any(|sol| sol.canonical == typed));
+        assert_eq!(get_canonical_expression("4 x (1 + 9"), None);
+    }
+
+    #[test]
+    fn first_solution_agrees_with_the_full_search() {
+        let values = vec!(Fraction::from(2), Fraction::from(3), Fraction::from(7));
+        let first = find_first_solution(&values, Fraction::from(13)).expect("");
+        assert_eq!(first.canonical, find_solutions(&values, Fraction::from(13)).first().expect("").canonical);
+        assert!(find_first_solution(&values, Fraction::from(1000)).is_none());
+    }
+
+    #[test]
+    fn only_parens_wrapping_everything_are_stripped() {
+        assert_eq!(strip_outer_parens("((9 + 1) x 4)"), "(9 + 1) x 4");
+        assert_eq!(strip_outer_parens("(1 + 2) x (3 + 4)"), "(1 + 2) x (3 + 4)");
+        assert_eq!(strip_outer_parens("((1 + 2) x (3 + 4))"), "(1 + 2) x (3 + 4)");
+        assert_eq!(strip_outer_parens("(1 + 2"), "(1 + 2");
+        assert_eq!(strip_outer_parens("1 + 2"), "1 + 2");
+        assert_eq!(get_canonical_expression("(3 + 4) x (1 + 2)"), Some("(1 + 2) x (3 + 4)".to_string()));
+    }
+}
@@ -0,0 +1,214 @@
+use crate::util::clipboard::Clipboard;
+use ggez::input::keyboard::{KeyCode, KeyMods};
+
+/* #region Public Interface */
+/// One edit to whichever text field has focus, in the order the player made them.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TextEvent {
+    Character(char),
+    Backspace,
+    Delete,
+    CursorLeft,
+    CursorRight,
+    Home,
+    End,
+    Copy,
+    Paste(String) //What was on the clipboard when it was read
+}
+
+/// Typed characters and editing keys for the current frame. Only the consumer that has text focus is
+/// handed the channel; everything else sees none of it.
+pub struct TextInputChannel {
+    events: Vec<TextEvent>,
+    system_clipboard: Clipboard,
+    clipboard: String //Last text copied in the game; pasted when the system clipboard can't be read
+}
+impl TextInputChannel {
+    pub fn new() -> TextInputChannel {
+        TextInputChannel { events: Vec::new(), system_clipboard: Clipboard::new(), clipboard: String::new() }
+    }
+    pub fn push_character(&mut self, character: char) {
+        if !character.is_control() {
+            self.events.push(TextEvent::Character(character));
+        }
+    }
+    /// Adds an edit as-is, e.g. one played back from a recording.
+    pub fn push_event(&mut self, event: TextEvent) {
+        self.events.push(event);
+    }
+    /// Turns the fixed text-editing keys into edit events. These don't go through the rebindable controls,
+    /// so they behave the same in every text field. Returns whether the key was one of them.
+    /// Pasting only starts a clipboard read; the text arrives through `poll_clipboard`.
+    pub fn push_key(&mut self, key: KeyCode, mods: KeyMods) -> bool {
+        let event = match key {
+            KeyCode::Back => TextEvent::Backspace,
+            KeyCode::Delete => TextEvent::Delete,
+            KeyCode::Left => TextEvent::CursorLeft,
+            KeyCode::Right => TextEvent::CursorRight,
+            KeyCode::Home => TextEvent::Home,
+            KeyCode::End => TextEvent::End,
+            KeyCode::C if mods.contains(KeyMods::CTRL) => TextEvent::Copy,
+            KeyCode::V if mods.contains(KeyMods::CTRL) => { return self.request_paste(); },
+            KeyCode::Insert if mods.contains(KeyMods::SHIFT) => { return self.request_paste(); },
+            _ => { return false; }
+        };
+        self.events.push(event);
+        true
+    }
+    pub fn get_events(&self) -> &[TextEvent] {
+        self.events.as_slice()
+    }
+    /// Copies to the system clipboard, keeping a copy in the game in case it can't be written or read back.
+    pub fn set_clipboard(&mut self, text: &str) {
+        self.clipboard = text.to_string();
+        self.system_clipboard.set_text(text);
+    }
+    /// Adds a paste for every clipboard read that has finished. Call once a frame, before the events are handed out.
+    pub fn poll_clipboard(&mut self) {
+        for pasted in self.system_clipboard.take_pasted() {
+            let text = pasted.unwrap_or_else(|| self.clipboard.clone());
+            self.events.push(TextEvent::Paste(clean_pasted(&text)));
+        }
+    }
+    /// Drops this frame's events, whether or not anyone had focus to read them.
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+    fn request_paste(&mut self) -> bool {
+        self.system_clipboard.request_text();
+        true
+    }
+}
+
+/// Editable single line of text with a cursor. Characters the filter rejects are never inserted,
+/// whether typed or pasted.
+pub struct TextField {
+    text: Vec<char>,
+    cursor: usize, //Characters before the cursor
+    filter: fn(char) -> bool
+}
+impl TextField {
+    pub fn new() -> TextField {
+        TextField { text: Vec::new(), cursor: 0, filter: |_| true }
+    }
+    pub fn with_filter(mut self, filter: fn(char) -> bool) -> TextField {
+        self.filter = filter;
+        self
+    }
+    pub fn get_text(&self) -> String {
+        self.text.iter().collect()
+    }
+    /// The text with `caret` inserted where the cursor is.
+    pub fn get_text_with_caret(&self, caret: char) -> String {
+        let mut display: String = self.text[..self.cursor].iter().collect();
+        display.push(caret);
+        display.extend(self.text[self.cursor..].iter());
+        display
+    }
+    pub fn clear(&mut self) {
+        self.text.clear();
+        self.cursor = 0;
+    }
+    pub fn insert(&mut self, text: &str) {
+        for character in text.chars().filter(|c| (self.filter)(*c)) {
+            self.text.insert(self.cursor, character);
+            self.cursor += 1;
+        }
+    }
+    /// Applies every edit in the channel. Returns whether the text changed.
+    pub fn process_text_input(&mut self, channel: &mut TextInputChannel) -> bool {
+        let before = self.text.clone();
+        for event in channel.get_events().to_vec() {
+            match event {
+                TextEvent::Character(character) => { self.insert(&character.to_string()); },
+                TextEvent::Backspace => {
+                    if self.cursor > 0 {
+                        self.cursor -= 1;
+                        self.text.remove(self.cursor);
+                    }
+                },
+                TextEvent::Delete => {
+                    if self.cursor < self.text.len() { self.text.remove(self.cursor); }
+                },
+                TextEvent::CursorLeft => { self.cursor = self.cursor.saturating_sub(1); },
+                TextEvent::CursorRight => { self.cursor = (self.cursor+1).min(self.text.len()); },
+                TextEvent::Home => { self.cursor = 0; },
+                TextEvent::End => { self.cursor = self.text.len(); },
+                TextEvent::Copy => { channel.set_clipboard(&self.get_text()); },
+                TextEvent::Paste(pasted) => { self.insert(&pasted); }
+            }
+        }
+        self.text != before
+    }
+}
+/* #endregion */
+
+/* #region Secret Inner-Workings */
+//Fields are a single line, so line breaks and other control characters are dropped
+fn clean_pasted(text: &str) -> String {
+    text.chars().filter(|c| !c.is_control()).collect()
+}
+/* #endregion */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(field: &mut TextField, events: &[TextEvent]) -> bool {
+        let mut channel = TextInputChannel::new();
+        for event in events {
+            channel.push_event(event.clone());
+        }
+        field.process_text_input(&mut channel)
+    }
+    fn typed(text: &str) -> Vec<TextEvent> {
+        text.chars().map(TextEvent::Character).collect()
+    }
+
+    #[test]
+    fn the_cursor_stays_within_the_text() {
+        let mut field = TextField::new();
+        edit(&mut field, &typed("abc"));
+        edit(&mut field, &[TextEvent::CursorLeft, TextEvent::CursorLeft, TextEvent::Character('x')]);
+        assert_eq!(field.get_text_with_caret('|'), "ax|bc");
+        assert!(!edit(&mut field, &vec!(TextEvent::CursorLeft; 5)));
+        assert_eq!(field.get_text_with_caret('|'), "|axbc");
+        edit(&mut field, &vec!(TextEvent::CursorRight; 9));
+        assert_eq!(field.get_text_with_caret('|'), "axbc|");
+    }
+
+    #[test]
+    fn home_and_end_jump_to_either_end() {
+        let mut field = TextField::new();
+        edit(&mut field, &typed("12"));
+        edit(&mut field, &[TextEvent::Home, TextEvent::Character('+')]);
+        assert_eq!(field.get_text_with_caret('|'), "+|12");
+        edit(&mut field, &[TextEvent::End, TextEvent::Character('-')]);
+        assert_eq!(field.get_text_with_caret('|'), "+12-|");
+    }
+
+    #[test]
+    fn backspace_and_delete_stop_at_the_ends() {
+        let mut field = TextField::new();
+        edit(&mut field, &typed("abc"));
+        assert!(!edit(&mut field, &[TextEvent::Delete])); //Nothing after the cursor
+        assert!(edit(&mut field, &[TextEvent::Backspace]));
+        assert_eq!(field.get_text_with_caret('|'), "ab|");
+        edit(&mut field, &[TextEvent::Home]);
+        assert!(!edit(&mut field, &[TextEvent::Backspace])); //Nothing before it
+        assert!(edit(&mut field, &[TextEvent::Delete]));
+        assert_eq!(field.get_text_with_caret('|'), "|b");
+        edit(&mut field, &[TextEvent::Delete, TextEvent::Delete, TextEvent::Backspace]);
+        assert_eq!(field.get_text_with_caret('|'), "|");
+    }
+
+    #[test]
+    fn pasted_text_goes_through_the_filter() {
+        let mut field = TextField::new().with_filter(|c| c.is_ascii_digit() || c == '+');
+        edit(&mut field, &typed("1"));
+        edit(&mut field, &[TextEvent::Home, TextEvent::Paste(clean_pasted("4 x 6\n+z\t2"))]);
+        assert_eq!(field.get_text_with_caret('|'), "46+2|1");
+        assert!(!edit(&mut field, &[TextEvent::Paste("abc".to_string())]));
+        assert_eq!(clean_pasted("a\r\nb\tc"), "abc");
+    }
+}
@@ -0,0 +1,66 @@
+pub struct Countdown {
+    duration: f32,
+    remaining: f32
+}
+impl Countdown {
+    pub fn new(duration: f32) -> Countdown {
+        Countdown { duration, remaining: duration }
+    }
+    /// Advances the countdown by `delta` seconds. Returns true only on the tick that it runs out.
+    pub fn tick(&mut self, delta: f32) -> bool {
+        if self.is_expired() { return false; }
+        self.remaining = (self.remaining - delta).max(0.0);
+        return self.is_expired();
+    }
+    pub fn reset(&mut self) {
+        self.remaining = self.duration;
+    }
+    pub fn is_expired(&self) -> bool {
+        self.remaining <= 0.0
+    }
+    pub fn get_remaining(&self) -> f32 {
+        self.remaining
+    }
+}
+
+/// Formats a number of seconds as m:ss, rounding up so that 0:00 only shows once time has run out.
+pub fn format_seconds(seconds: f32) -> String {
+    let whole = seconds.max(0.0).ceil() as u32;
+    format!("{}:{:02}", whole/60, whole%60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn countdown_expires_once() {
+        let mut countdown = Countdown::new(1.0);
+        assert!(!countdown.tick(0.4));
+        assert!((countdown.get_remaining()-0.6).abs() < 1e-6);
+        assert!(!countdown.tick(0.4));
+        assert!(countdown.tick(0.4)); //Runs out part way through the tick
+        assert_eq!(countdown.get_remaining(), 0.0);
+        assert!(countdown.is_expired());
+        assert!(!countdown.tick(0.4)); //Already reported
+    }
+
+    #[test]
+    fn reset_winds_the_countdown_back_up() {
+        let mut countdown = Countdown::new(1.0);
+        countdown.tick(5.0);
+        countdown.reset();
+        assert!(!countdown.is_expired());
+        assert_eq!(countdown.get_remaining(), 1.0);
+        assert!(countdown.tick(1.0));
+    }
+
+    #[test]
+    fn seconds_round_up_to_the_next_whole_second() {
+        assert_eq!(format_seconds(0.0), "0:00");
+        assert_eq!(format_seconds(0.1), "0:01");
+        assert_eq!(format_seconds(59.5), "1:00");
+        assert_eq!(format_seconds(125.0), "2:05");
+        assert_eq!(format_seconds(-3.0), "0:00");
+    }
+}
@@ -0,0 +1,37 @@
+/* #region Public Interface */
+/// Size the game's layouts are authored against. Everything is scaled from here to the real window.
+pub const REFERENCE_WIDTH: f32 = 960.0;
+pub const REFERENCE_HEIGHT: f32 = 540.0;
+
+/// Logical size of the window, as last reported by `resize_event`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    width: f32,
+    height: f32
+}
+impl Viewport {
+    pub fn new(width: f32, height: f32) -> Viewport {
+        //Clamped so a degenerate size can never produce a zero scale
+        Viewport { width: width.max(1.0), height: height.max(1.0) }
+    }
+    pub fn reference() -> Viewport {
+        Viewport::new(REFERENCE_WIDTH, REFERENCE_HEIGHT)
+    }
+    pub fn get_size(&self) -> (f32, f32) {
+        (self.width, self.height)
+    }
+    /// Uniform scale that fits the reference size inside the window without stretching.
+    pub fn get_scale(&self) -> f32 {
+        (self.width/REFERENCE_WIDTH).min(self.height/REFERENCE_HEIGHT)
+    }
+    /// Point at the given fraction of the window, e.g. (0.5, 0.5) is the center.
+    pub fn anchor(&self, x_percent: f32, y_percent: f32) -> (f32, f32) {
+        (self.width*x_percent, self.height*y_percent)
+    }
+    /// Top-left corner of the reference-sized area once it is scaled and centered in the window.
+    pub fn get_reference_origin(&self) -> (f32, f32) {
+        let scale = self.get_scale();
+        ((self.width-REFERENCE_WIDTH*scale)/2.0, (self.height-REFERENCE_HEIGHT*scale)/2.0)
+    }
+}
+/* #endregion */